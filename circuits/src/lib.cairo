@@ -14,6 +14,8 @@ struct Player {
 struct OrbitalNFT {
     id:    u256,
     owner: AlkaneId,
+    // opaque game attributes; carried through untouched by transfers
+    data:  u256,
 }
 
 #[derive(Drop, Serde)]
@@ -84,16 +86,24 @@ fn update_nft_owner(ref nfts: Array<OrbitalNFT>, nid: u256, new_owner: AlkaneId)
 }
 
 
+// Trailing u64 is the sender nonce. Ordering is enforced by the sequencer's
+// State; the circuit only carries it so it is bound into the trace.
+// MintChips credits an L1 bridge deposit and has no sender, hence no nonce;
+// BurnChips is the matching withdrawal intent.
 #[derive(Drop, Copy, Serde)]
 enum Transaction {
-    TransferChips:(AlkaneId, AlkaneId, u256),
-    TransferNFT  :(AlkaneId, AlkaneId, u256),
+    TransferChips:(AlkaneId, AlkaneId, u256, u64),
+    TransferNFT  :(AlkaneId, AlkaneId, u256, u64),
+    MintChips    :(AlkaneId, u256),
+    BurnChips    :(AlkaneId, u256, u64),
 }
 
 fn apply_tx(ref st: GameState, tx: Transaction) {
     match tx {
         Transaction::TransferChips(data) => {
-            let (from, to, amt) = data;
+            let (from, to, amt, _nonce) = data;
+            // Both balances are read before either write, so from == to would mint amt.
+            assert!(from != to, "self transfer");
             let from_p = find_player(@st.players, @from).expect('from missing');
             let to_p   = find_player(@st.players, @to  ).expect('to missing');
             assert!(from_p.chips_balance >= amt, "insufficient");
@@ -102,11 +112,24 @@ fn apply_tx(ref st: GameState, tx: Transaction) {
             update_player_balance(ref st.players, to,   to_p.chips_balance   + amt);
         },
         Transaction::TransferNFT(data) => {
-            let (from, to, nid) = data;
+            let (from, to, nid, _nonce) = data;
             let nft   = find_nft(@st.nfts, @nid).expect('nft missing');
             assert!(nft.owner == from, "not owner");
             update_nft_owner(ref st.nfts, nid, to);
         },
+        Transaction::MintChips(data) => {
+            let (to, amt) = data;
+            match find_player(@st.players, @to) {
+                Option::Some(p) => update_player_balance(ref st.players, to, p.chips_balance + amt),
+                Option::None(()) => st.players.append(Player { id: to, chips_balance: amt }),
+            }
+        },
+        Transaction::BurnChips(data) => {
+            let (from, amt, _nonce) = data;
+            let from_p = find_player(@st.players, @from).expect('from missing');
+            assert!(from_p.chips_balance >= amt, "insufficient");
+            update_player_balance(ref st.players, from, from_p.chips_balance - amt);
+        },
     }
 }
 
@@ -128,6 +151,8 @@ fn process(mut st: GameState, txs: Array<Transaction>) -> GameState {
 //   2. nfts    array (ptr,len)
 //   3. txs     array (ptr,len)
 // Return value: two felts (low, high) – 256-bit root placeholder.
+// Leaves are hashed sequencer-side (`hash_leaf` in sequencer/src/state.rs);
+// any in-circuit leaf hashing must use that same domain-separated preimage.
 //
 
 fn main(raw : Array<felt252>) -> Array<felt252> {
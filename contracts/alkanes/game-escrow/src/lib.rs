@@ -28,6 +28,7 @@ use alkanes_runtime::{
 use alkanes_runtime::message::MessageDispatch;
 use metashrew_support::index_pointer::KeyValuePointer;
 use metashrew_support::compat::to_arraybuffer_layout;
+use metashrew_support::utils::consume_u128;
 use alkanes_support::{
     cellpack::Cellpack,
     context::Context,
@@ -35,9 +36,20 @@ use alkanes_support::{
     parcel::{AlkaneTransfer, AlkaneTransferParcel},
     response::CallResponse,
 };
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use error_codes::ErrorCode;
+use std::io::Cursor;
 use std::sync::Arc;
 
+/// Alkanes factory space: calling `[6, n]` instantiates a copy of template `[4, n]`.
+const FACTORY_BLOCK: u128 = 6;
+
+/// Block the factory copies templates from.
+const TEMPLATE_BLOCK: u128 = 4;
+
+/// Position Token opcode run on creation; takes the position id it represents.
+const POSITION_TOKEN_INIT_OPCODE: u128 = 0;
+
 // --- Storage Pointers ---
 
 /// Points to the AlkaneId of the Position Token implementation contract.
@@ -45,6 +57,16 @@ fn position_token_implementation_pointer() -> StoragePointer {
     StoragePointer::from_keyword("/position_token_impl")
 }
 
+/// Points to the AlkaneId of the Verifier contract allowed to finalize state.
+fn verifier_pointer() -> StoragePointer {
+    StoragePointer::from_keyword("/verifier_id")
+}
+
+/// Points to the settlement watermark: positions with id below it are settled.
+fn settled_position_count_pointer() -> StoragePointer {
+    StoragePointer::from_keyword("/settled_position_count")
+}
+
 /// Points to the initialization flag.
 fn initialized_pointer() -> StoragePointer {
     StoragePointer::from_keyword("/initialized")
@@ -55,6 +77,46 @@ fn paused_pointer() -> StoragePointer {
     StoragePointer::from_keyword("/paused")
 }
 
+/// Points to the number of positions allocated so far (also the next id).
+fn position_count_pointer() -> StoragePointer {
+    StoragePointer::from_keyword("/position_count")
+}
+
+/// Points to the serialized `AlkaneTransferParcel` deposited under a position.
+fn position_pointer(position_id: u128) -> StoragePointer {
+    StoragePointer::from_keyword("/position/").select(&position_id.to_le_bytes().to_vec())
+}
+
+/// Points to the depositor AlkaneId of a position.
+fn position_owner_pointer(position_id: u128) -> StoragePointer {
+    position_pointer(position_id).keyword("/owner")
+}
+
+/// Points to the AlkaneId of the Position Token minted for a position.
+fn position_token_pointer(position_id: u128) -> StoragePointer {
+    position_pointer(position_id).keyword("/token")
+}
+
+/// Set to 1 for each token a position took in as an NFT (a value-1 transfer).
+fn position_nft_pointer(position_id: u128, token: &AlkaneId) -> StoragePointer {
+    position_pointer(position_id).keyword("/nft/").select(&(*token).into())
+}
+
+/// Points to the depositor currently holding NFT `token` in escrow.
+fn nft_owner_pointer(token: &AlkaneId) -> StoragePointer {
+    StoragePointer::from_keyword("/nft/").select(&(*token).into())
+}
+
+/// Points to `owner`'s recorded balance of fungible `token`.
+fn ft_balance_pointer(owner: &AlkaneId, token: &AlkaneId) -> StoragePointer {
+    StoragePointer::from_keyword("/ft/").select(&(*owner).into()).keyword("/").select(&(*token).into())
+}
+
+/// Reverse map: Position Token AlkaneId -> position id (u128).
+fn token_position_pointer(token: &AlkaneId) -> StoragePointer {
+    StoragePointer::from_keyword("/position_token/").select(&token.clone().into())
+}
+
 
 
 
@@ -69,18 +131,69 @@ impl AlkaneResponder for GameEscrow {}
 impl AuthenticatedResponder for GameEscrow {}
 // Not a token; maintains balances and ownership state for deposits.
 
+/// Creates the Position Token for a freshly recorded position.
+trait PositionTokenMinter {
+    /// Returns the transfers produced by minting, expected to be exactly one NFT.
+    fn mint_position_token(&self, implementation: AlkaneId, position_id: u128) -> Result<AlkaneTransferParcel>;
+}
+
+#[cfg(not(test))]
+impl PositionTokenMinter for GameEscrow {
+    fn mint_position_token(&self, implementation: AlkaneId, position_id: u128) -> Result<AlkaneTransferParcel> {
+        let cellpack = Cellpack {
+            target: AlkaneId { block: FACTORY_BLOCK, tx: implementation.tx },
+            inputs: vec![POSITION_TOKEN_INIT_OPCODE, position_id],
+        };
+        let response = self.call(&cellpack, &AlkaneTransferParcel::default(), self.fuel())?;
+        Ok(response.alkanes)
+    }
+}
+
+#[cfg(test)]
+impl PositionTokenMinter for GameEscrow {
+    fn mint_position_token(&self, implementation: AlkaneId, position_id: u128) -> Result<AlkaneTransferParcel> {
+        // Stand-in for the factory: one unit of a new `[2, impl.tx + 1 + position_id]` alkane.
+        Ok(AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: AlkaneId { block: 2, tx: implementation.tx + 1 + position_id },
+            value: 1,
+        }]))
+    }
+}
+
 #[derive(MessageDispatch)]
 pub enum GameEscrowMessage {
     /// Initializes the contract (idempotent once).
     #[opcode(0)]
     Initialize { verifier: AlkaneId },
-    /// Accept deposits from incoming_alkanes
+    /// Accept deposits from incoming_alkanes; mints a Position Token to the caller
+    /// and returns the new position id (u128 LE)
     #[opcode(1)]
     Deposit,
+    /// Redeem `amount` of one token from the position of the incoming Position Token;
+    /// the token is returned while any balance remains
+    #[opcode(3)]
+    WithdrawPartial { token_block: u128, token_tx: u128, amount: u128 },
+    /// Depositor-only: withdraw `amount` of `[token_block, token_tx]` from one of
+    /// the caller's positions and its FT balance
+    #[opcode(4)]
+    WithdrawFt { position_id: u128, token_block: u128, token_tx: u128, amount: u128 },
     /// DAO-only: set paused flag
     #[opcode(6)]
     SetPaused { paused: u128 },
-    // reserved for future view methods
+    /// Verifier-only: marks every position opened so far as settled
+    #[opcode(7)]
+    OnStateFinalized,
+    /// DAO-only: set the Position Token template deposits mint from (`[6, impl.tx]`)
+    #[opcode(8)]
+    SetPositionTokenImpl { impl_id: AlkaneId },
+    /// Serialized `AlkaneTransferParcel` redeemable by a position; empty if unknown
+    #[opcode(10)]
+    #[returns(Vec<u8>)]
+    GetPosition { position_id: u128 },
+    /// Depositor AlkaneId bytes of a position; empty if unknown
+    #[opcode(11)]
+    #[returns(Vec<u8>)]
+    GetPositionOwner { position_id: u128 },
 }
 
 impl GameEscrow {
@@ -95,38 +208,142 @@ impl GameEscrow {
     }
 
 
+    /// Helper to check whether the immediate caller is the stored Verifier.
+    fn caller_is_verifier(&self, ctx: &Context) -> bool {
+        let stored = verifier_pointer().get().as_ref().clone();
+        !stored.is_empty() && stored == Vec::<u8>::from(ctx.caller)
+    }
+
     /// Initializes the contract. Can only be called once.
-    fn initialize(&self, _verifier: AlkaneId) -> Result<CallResponse> {
+    fn initialize(&self, verifier: AlkaneId) -> Result<CallResponse> {
         if self.is_initialized() {
-            return Err(anyhow!("Contract already initialized"));
+            return Err(ErrorCode::AlreadyInitialized.into());
         }
 
+        verifier_pointer().set(Arc::new(verifier.into()));
+
         // Set the initialized flag
         initialized_pointer().set_value::<u8>(1);
 
         Ok(CallResponse::default())
     }
 
+    /// Reads the Position Token template id used for minting.
+    fn position_token_implementation(&self) -> Result<AlkaneId> {
+        let bytes = position_token_implementation_pointer().get().as_ref().clone();
+        if bytes.is_empty() {
+            return Err(ErrorCode::ImplNotSet.into());
+        }
+        let mut cursor = Cursor::<Vec<u8>>::new(bytes);
+        Ok(AlkaneId::new(consume_u128(&mut cursor)?, consume_u128(&mut cursor)?))
+    }
+
+    /// Finds the Position Token among `incoming` and returns its position id and id.
+    fn incoming_position(&self, incoming: &AlkaneTransferParcel) -> Result<(u128, AlkaneId)> {
+        for t in incoming.0.iter() {
+            if t.value != 1 {
+                continue;
+            }
+            let mapped = token_position_pointer(&t.id);
+            if mapped.get().len() == 0 {
+                continue;
+            }
+            let position_id = mapped.get_value::<u128>();
+            if position_token_pointer(position_id).get().as_ref().clone() == Vec::<u8>::from(t.id) {
+                return Ok((position_id, t.id));
+            }
+        }
+        Err(ErrorCode::InvalidPositionToken.into())
+    }
+
+    /// Reads the parcel still redeemable by a position.
+    fn position_parcel(&self, position_id: u128) -> Result<AlkaneTransferParcel> {
+        let bytes = position_pointer(position_id).get().as_ref().clone();
+        if bytes.is_empty() {
+            return Err(ErrorCode::UnknownPosition.into());
+        }
+        AlkaneTransferParcel::parse(&mut Cursor::new(bytes))
+    }
+
     fn deposit(&self) -> Result<CallResponse> {
-        if self.is_paused() { return Err(anyhow!("PAUSED")); }
         let ctx = self.context()?;
-        let caller = ctx.caller;
-        let input = ctx.incoming_alkanes;
-
-        for t in input.0.iter() {
-            if t.value == 1 {
-                // NFT ownership map: /nft/<id> -> owner AlkaneId bytes
-                let mut p = StoragePointer::from_keyword("/nft/").select(&t.id.clone().into());
-                p.set(Arc::new(caller.into()));
-            } else if t.value > 1 {
-                // FT balances: /ft/<caller>/<token>
-                let mut p = StoragePointer::from_keyword("/ft/")
-                    .select(&caller.into()).keyword("/").select(&t.id.clone().into());
-                let prev = p.get_value::<u128>();
-                p.set_value::<u128>(prev.saturating_add(t.value));
-            }
+        self.record_deposit(ctx.caller, ctx.incoming_alkanes)
+    }
+
+    /// Records `input` as one position owned by `caller` and returns its id.
+    ///
+    /// Every transfer is validated and the Position Token minted before any
+    /// storage is written, so a rejected deposit leaves no partial state.
+    fn record_deposit(&self, caller: AlkaneId, input: AlkaneTransferParcel) -> Result<CallResponse> {
+        if self.is_paused() { return Err(ErrorCode::Paused.into()); }
+        if input.0.is_empty() { return Err(ErrorCode::EmptyDeposit.into()); }
+        if input.0.iter().any(|t| t.value == 0) { return Err(ErrorCode::InvalidDeposit.into()); }
+        // An NFT's id may not appear twice, or redemption could not tell which
+        // of its transfers the FT ledger backs.
+        if input.0.iter().any(|n| n.value == 1 && input.0.iter().filter(|t| t.id == n.id).count() > 1) {
+            return Err(ErrorCode::InvalidDeposit.into());
         }
-        Ok(CallResponse::default())
+        let implementation = self.position_token_implementation()?;
+
+        // Work out every new FT balance up front so an overflow rejects the
+        // deposit before anything is written.
+        let mut balances: Vec<(AlkaneId, u128)> = Vec::new();
+        for t in input.0.iter().filter(|t| t.value > 1) {
+            let i = match balances.iter().position(|(id, _)| *id == t.id) {
+                Some(i) => i,
+                None => {
+                    balances.push((t.id, ft_balance_pointer(&caller, &t.id).get_value::<u128>()));
+                    balances.len() - 1
+                }
+            };
+            balances[i].1 = balances[i].1.checked_add(t.value).ok_or(ErrorCode::DepositOverflow)?;
+        }
+
+        // Mint the claim check; its AlkaneId maps back to this position on redemption.
+        let position_id = position_count_pointer().get_value::<u128>();
+        let minted = self.mint_position_token(implementation, position_id)?;
+        let token = match minted.0.as_slice() {
+            [t] if t.value == 1 => t.id.clone(),
+            _ => return Err(ErrorCode::BadPositionToken.into()),
+        };
+
+        for t in input.0.iter().filter(|t| t.value == 1) {
+            nft_owner_pointer(&t.id).set(Arc::new(caller.into()));
+            position_nft_pointer(position_id, &t.id).set_value::<u8>(1);
+        }
+        for (token, balance) in balances {
+            ft_balance_pointer(&caller, &token).set_value::<u128>(balance);
+        }
+
+        // Keep the whole parcel grouped under one id so it can be redeemed as a unit.
+        position_count_pointer().set_value::<u128>(position_id + 1);
+        position_pointer(position_id).set(Arc::new(input.serialize()));
+        position_owner_pointer(position_id).set(Arc::new(caller.into()));
+        position_token_pointer(position_id).set(Arc::new(token.clone().into()));
+        token_position_pointer(&token).set_value::<u128>(position_id);
+
+        let mut resp = CallResponse::default();
+        resp.alkanes = minted;
+        resp.data = position_id.to_le_bytes().to_vec();
+        Ok(resp)
+    }
+
+    fn on_state_finalized(&self) -> Result<CallResponse> {
+        let ctx = self.context()?;
+        self.finalize_positions(&ctx)
+    }
+
+    /// Advances the settlement watermark to the current position count and returns it.
+    fn finalize_positions(&self, ctx: &Context) -> Result<CallResponse> {
+        if !self.caller_is_verifier(ctx) {
+            return Err(ErrorCode::Unauthorized.into());
+        }
+        let settled = position_count_pointer().get_value::<u128>();
+        settled_position_count_pointer().set_value::<u128>(settled);
+
+        let mut resp = CallResponse::default();
+        resp.data = settled.to_le_bytes().to_vec();
+        Ok(resp)
     }
 
     fn set_paused(&self, paused: u128) -> Result<CallResponse> {
@@ -135,9 +352,599 @@ impl GameEscrow {
         Ok(CallResponse::default())
     }
 
-    // no other methods for now
+    fn set_position_token_impl(&self, impl_id: AlkaneId) -> Result<CallResponse> {
+        self.only_owner()?;
+        let ctx = self.context()?;
+        self.store_position_token_impl(ctx.myself, impl_id)
+    }
+
+    /// Points future mints at `impl_id`; the escrow itself can never be the template.
+    ///
+    /// Only `[4, n]` is accepted: mints call `[6, n]`, which copies `[4, n]`
+    /// whatever block was stored, so any other block would not be what runs.
+    fn store_position_token_impl(&self, myself: AlkaneId, impl_id: AlkaneId) -> Result<CallResponse> {
+        if impl_id == myself {
+            return Err(ErrorCode::ImplIsSelf.into());
+        }
+        if impl_id.block != TEMPLATE_BLOCK {
+            return Err(ErrorCode::ImplNotTemplate.into());
+        }
+        position_token_implementation_pointer().set(Arc::new(impl_id.into()));
+        Ok(CallResponse::default())
+    }
+
+    fn withdraw_partial(&self, token_block: u128, token_tx: u128, amount: u128) -> Result<CallResponse> {
+        let ctx = self.context()?;
+        self.redeem_partial(ctx.incoming_alkanes, AlkaneId::new(token_block, token_tx), amount)
+    }
+
+    /// Pays out `amount` of `token` from the position proven by `incoming`.
+    fn redeem_partial(&self, incoming: AlkaneTransferParcel, token: AlkaneId, amount: u128) -> Result<CallResponse> {
+        if self.is_paused() { return Err(ErrorCode::Paused.into()); }
+        if amount == 0 { return Err(ErrorCode::InvalidAmount.into()); }
+        let (position_id, position_token) = self.incoming_position(&incoming)?;
+        let parcel = self.debit_position(position_id, token, amount)?;
+
+        let mut resp = CallResponse::default();
+        resp.alkanes.0.push(AlkaneTransfer { id: token, value: amount });
+        // Give the claim check back while something is left to redeem; once the
+        // position is drained the token stays here, closing it.
+        if !parcel.0.is_empty() {
+            resp.alkanes.0.push(AlkaneTransfer { id: position_token, value: 1 });
+        }
+        // Refund anything else that was sent along.
+        for t in incoming.0.iter() {
+            if t.id != position_token {
+                resp.alkanes.0.push(t.clone());
+            }
+        }
+        Ok(resp)
+    }
+
+    /// Takes `amount` of `token` out of a position and returns what it still holds.
+    fn debit_position(&self, position_id: u128, token: AlkaneId, amount: u128) -> Result<AlkaneTransferParcel> {
+        let mut parcel = self.position_parcel(position_id)?;
+
+        let recorded = parcel
+            .0
+            .iter_mut()
+            .find(|t| t.id == token)
+            .ok_or(ErrorCode::InsufficientPositionBalance)?;
+        recorded.value = recorded
+            .value
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientPositionBalance)?;
+        parcel.0.retain(|t| t.value > 0);
+
+        // Keep the depositor's FT ledger in step with what left escrow.
+        // NFTs have no ledger entry: redeeming one releases its `/nft/` record
+        // instead, unless a later deposit of the same id now holds it.
+        let owner = position_owner_pointer(position_id).get().as_ref().clone();
+        if position_nft_pointer(position_id, &token).get_value::<u8>() == 1 {
+            let mut nft_owner = nft_owner_pointer(&token);
+            if nft_owner.get().as_ref().clone() == owner {
+                nft_owner.set(Arc::new(Vec::new()));
+            }
+        } else {
+            let mut ft = StoragePointer::from_keyword("/ft/").select(&owner).keyword("/").select(&token.into());
+            let remaining = ft
+                .get_value::<u128>()
+                .checked_sub(amount)
+                .ok_or(ErrorCode::InsufficientFtBalance)?;
+            ft.set_value::<u128>(remaining);
+        }
+        position_pointer(position_id).set(Arc::new(parcel.serialize()));
+        Ok(parcel)
+    }
+
+    fn withdraw_ft(&self, position_id: u128, token_block: u128, token_tx: u128, amount: u128) -> Result<CallResponse> {
+        let ctx = self.context()?;
+        self.debit_ft(ctx.caller, position_id, AlkaneId::new(token_block, token_tx), amount)
+    }
+
+    /// Pays `amount` of fungible `token` out of `caller`'s position `position_id`.
+    ///
+    /// The position is debited with the ledger, so whoever holds its Position
+    /// Token afterwards can redeem exactly what `GetPosition` still reports.
+    fn debit_ft(&self, caller: AlkaneId, position_id: u128, token: AlkaneId, amount: u128) -> Result<CallResponse> {
+        if self.is_paused() { return Err(ErrorCode::Paused.into()); }
+        if amount == 0 { return Err(ErrorCode::InvalidAmount.into()); }
+        if position_owner_pointer(position_id).get().as_ref().clone() != Vec::<u8>::from(caller) {
+            return Err(ErrorCode::Unauthorized.into());
+        }
+        if position_nft_pointer(position_id, &token).get_value::<u8>() == 1 {
+            return Err(ErrorCode::InsufficientFtBalance.into());
+        }
+        self.debit_position(position_id, token, amount)?;
+
+        let mut resp = CallResponse::default();
+        resp.alkanes.0.push(AlkaneTransfer { id: token, value: amount });
+        Ok(resp)
+    }
+
+    fn get_position(&self, position_id: u128) -> Result<CallResponse> {
+        let mut resp = CallResponse::default();
+        resp.data = position_pointer(position_id).get().as_ref().clone();
+        Ok(resp)
+    }
+
+    fn get_position_owner(&self, position_id: u128) -> Result<CallResponse> {
+        let mut resp = CallResponse::default();
+        resp.data = position_owner_pointer(position_id).get().as_ref().clone();
+        Ok(resp)
+    }
 }
 
 declare_alkane! {
     impl AlkaneResponder for GameEscrow { type Message = GameEscrowMessage; }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// Stub Position Token template; see the `#[cfg(test)]` `PositionTokenMinter`.
+    const STUB_IMPL: AlkaneId = AlkaneId { block: 4, tx: 0x5a7c };
+
+    fn reset_test_storage() {
+        for k in [
+            "/initialized",
+            "/paused",
+            "/position_count",
+            "/position_token_impl",
+            "/verifier_id",
+            "/settled_position_count",
+        ] {
+            StoragePointer::from_keyword(k).set(Arc::new(Vec::new()));
+        }
+        position_token_implementation_pointer().set(Arc::new(STUB_IMPL.into()));
+    }
+
+    fn transfer(block: u128, tx: u128, value: u128) -> AlkaneTransfer {
+        AlkaneTransfer { id: AlkaneId { block, tx }, value }
+    }
+
+    fn fields(parcel: &AlkaneTransferParcel) -> Vec<(u128, u128, u128)> {
+        parcel.0.iter().map(|t| (t.id.block, t.id.tx, t.value)).collect()
+    }
+
+    fn position_id_of(resp: &CallResponse) -> u128 {
+        u128::from_le_bytes(resp.data.clone().try_into().expect("16-byte position id"))
+    }
+
+    #[wasm_bindgen_test]
+    fn deposits_get_distinct_position_ids() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let alice = AlkaneId { block: 2, tx: 100 };
+        let bob = AlkaneId { block: 2, tx: 101 };
+
+        let first = escrow.record_deposit(alice, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+        let second = escrow.record_deposit(bob, AlkaneTransferParcel(vec![transfer(2, 7, 1)])).unwrap();
+
+        assert_eq!(position_id_of(&first), 0);
+        assert_eq!(position_id_of(&second), 1);
+        assert_eq!(position_count_pointer().get_value::<u128>(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn stored_parcel_and_owner_match_deposit() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let alice = AlkaneId { block: 2, tx: 100 };
+        let parcel = AlkaneTransferParcel(vec![transfer(2, 1, 500), transfer(2, 7, 1)]);
+
+        let id = position_id_of(&escrow.record_deposit(alice, parcel.clone()).unwrap());
+
+        let stored = position_pointer(id).get().as_ref().clone();
+        let stored = AlkaneTransferParcel::parse(&mut Cursor::new(stored)).unwrap();
+        assert_eq!(fields(&stored), fields(&parcel));
+        let owner: Vec<u8> = alice.into();
+        assert_eq!(position_owner_pointer(id).get().as_ref().clone(), owner);
+    }
+
+    #[wasm_bindgen_test]
+    fn empty_deposit_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+
+        let err = escrow.record_deposit(AlkaneId { block: 2, tx: 100 }, AlkaneTransferParcel::default()).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::EmptyDeposit));
+        assert_eq!(position_count_pointer().get_value::<u128>(), 0);
+    }
+
+    fn ft_balance(owner: AlkaneId, token: AlkaneId) -> u128 {
+        ft_balance_pointer(&owner, &token).get_value::<u128>()
+    }
+
+    #[wasm_bindgen_test]
+    fn multi_asset_deposit_is_one_position() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let carol = AlkaneId { block: 2, tx: 200 };
+        let parcel = AlkaneTransferParcel(vec![transfer(2, 1, 500), transfer(2, 3, 40), transfer(2, 7, 1)]);
+
+        let resp = escrow.record_deposit(carol, parcel.clone()).unwrap();
+
+        assert_eq!(position_id_of(&resp), 0);
+        assert_eq!(position_count_pointer().get_value::<u128>(), 1);
+        assert_eq!(resp.alkanes.0.len(), 1);
+        assert_eq!(fields(&escrow.position_parcel(0).unwrap()), fields(&parcel));
+        assert_eq!(ft_balance(carol, AlkaneId { block: 2, tx: 1 }), 500);
+        assert_eq!(ft_balance(carol, AlkaneId { block: 2, tx: 3 }), 40);
+    }
+
+    #[wasm_bindgen_test]
+    fn zero_value_transfer_rejects_whole_deposit() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let dave = AlkaneId { block: 2, tx: 201 };
+        let parcel = AlkaneTransferParcel(vec![transfer(2, 1, 500), transfer(2, 3, 0)]);
+
+        let err = escrow.record_deposit(dave, parcel).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InvalidDeposit));
+        assert_eq!(position_count_pointer().get_value::<u128>(), 0);
+        assert!(position_pointer(0).get().as_ref().is_empty());
+        assert_eq!(ft_balance(dave, AlkaneId { block: 2, tx: 1 }), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn overflowing_deposit_is_rejected_without_writes() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let erin = AlkaneId { block: 2, tx: 202 };
+        let chips = AlkaneId { block: 2, tx: 1 };
+        ft_balance_pointer(&erin, &chips).set_value::<u128>(u128::MAX - 10);
+        // The first transfer alone would fit; the overflow is only hit at the second.
+        let parcel = AlkaneTransferParcel(vec![transfer(2, 5, 300), transfer(2, 1, 11)]);
+
+        let err = escrow.record_deposit(erin, parcel).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::DepositOverflow));
+        assert_eq!(ft_balance(erin, chips), u128::MAX - 10);
+        assert_eq!(ft_balance(erin, AlkaneId { block: 2, tx: 5 }), 0);
+        assert_eq!(position_count_pointer().get_value::<u128>(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn deposit_forwards_minted_position_token() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let alice = AlkaneId { block: 2, tx: 100 };
+
+        let resp = escrow.record_deposit(alice, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+
+        let id = position_id_of(&resp);
+        let token = AlkaneId { block: 2, tx: STUB_IMPL.tx + 1 + id };
+        assert_eq!(fields(&resp.alkanes), vec![(token.block, token.tx, 1)]);
+        assert_eq!(token_position_pointer(&token).get_value::<u128>(), id);
+        let token_bytes: Vec<u8> = token.into();
+        assert_eq!(position_token_pointer(id).get().as_ref().clone(), token_bytes);
+    }
+
+    #[wasm_bindgen_test]
+    fn position_views_read_back_deposit() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let alice = AlkaneId { block: 2, tx: 100 };
+        let parcel = AlkaneTransferParcel(vec![transfer(2, 1, 500), transfer(2, 7, 1)]);
+
+        let id = position_id_of(&escrow.record_deposit(alice, parcel.clone()).unwrap());
+
+        let data = escrow.get_position(id).unwrap().data;
+        let read = AlkaneTransferParcel::parse(&mut Cursor::new(data)).unwrap();
+        assert_eq!(fields(&read), fields(&parcel));
+        let owner = escrow.get_position_owner(id).unwrap().data;
+        let mut cursor = Cursor::new(owner);
+        assert_eq!(AlkaneId::new(consume_u128(&mut cursor).unwrap(), consume_u128(&mut cursor).unwrap()), alice);
+    }
+
+    #[wasm_bindgen_test]
+    fn position_views_are_empty_for_unknown_id() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+
+        assert!(escrow.get_position(999).unwrap().data.is_empty());
+        assert!(escrow.get_position_owner(999).unwrap().data.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn verifier_caller_settles_positions() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let verifier = AlkaneId { block: 2, tx: 9 };
+        escrow.initialize(verifier).unwrap();
+        escrow.record_deposit(AlkaneId { block: 2, tx: 100 }, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+        escrow.record_deposit(AlkaneId { block: 2, tx: 101 }, AlkaneTransferParcel(vec![transfer(2, 1, 700)])).unwrap();
+
+        let ctx = Context { caller: verifier, ..Default::default() };
+        assert!(escrow.caller_is_verifier(&ctx));
+        let resp = escrow.finalize_positions(&ctx).unwrap();
+
+        assert_eq!(resp.data, 2u128.to_le_bytes().to_vec());
+        assert_eq!(settled_position_count_pointer().get_value::<u128>(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn non_verifier_caller_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        escrow.initialize(AlkaneId { block: 2, tx: 9 }).unwrap();
+        escrow.record_deposit(AlkaneId { block: 2, tx: 100 }, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+
+        let ctx = Context { caller: AlkaneId { block: 2, tx: 100 }, ..Default::default() };
+        assert!(!escrow.caller_is_verifier(&ctx));
+        let err = escrow.finalize_positions(&ctx).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::Unauthorized));
+        assert_eq!(settled_position_count_pointer().get_value::<u128>(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn partial_withdraw_leaves_remainder_open() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let chips = AlkaneId { block: 2, tx: 1 };
+        let resp = escrow.record_deposit(AlkaneId { block: 2, tx: 100 }, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+        let id = position_id_of(&resp);
+        let position_token = resp.alkanes.0[0].clone();
+
+        let out = escrow.redeem_partial(AlkaneTransferParcel(vec![position_token.clone()]), chips, 200).unwrap();
+
+        assert_eq!(
+            fields(&out.alkanes),
+            vec![(2, 1, 200), (position_token.id.block, position_token.id.tx, 1)]
+        );
+        assert_eq!(fields(&escrow.position_parcel(id).unwrap()), vec![(2, 1, 300)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn withdrawing_everything_keeps_the_position_token() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let resp = escrow.record_deposit(AlkaneId { block: 2, tx: 100 }, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+        let position_token = resp.alkanes.0[0].clone();
+
+        let out = escrow.redeem_partial(AlkaneTransferParcel(vec![position_token]), AlkaneId { block: 2, tx: 1 }, 500).unwrap();
+
+        assert_eq!(fields(&out.alkanes), vec![(2, 1, 500)]);
+        assert!(escrow.position_parcel(position_id_of(&resp)).unwrap().0.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn over_withdraw_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let resp = escrow.record_deposit(AlkaneId { block: 2, tx: 100 }, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+        let id = position_id_of(&resp);
+        let position_token = resp.alkanes.0[0].clone();
+
+        let err = escrow
+            .redeem_partial(AlkaneTransferParcel(vec![position_token]), AlkaneId { block: 2, tx: 1 }, 501)
+            .unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InsufficientPositionBalance));
+        assert_eq!(fields(&escrow.position_parcel(id).unwrap()), vec![(2, 1, 500)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn withdraw_without_position_token_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        escrow.record_deposit(AlkaneId { block: 2, tx: 100 }, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+
+        let err = escrow
+            .redeem_partial(AlkaneTransferParcel(vec![transfer(2, 55, 1)]), AlkaneId { block: 2, tx: 1 }, 1)
+            .unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InvalidPositionToken));
+    }
+
+    #[wasm_bindgen_test]
+    fn ft_withdrawal_reduces_balance() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let frank = AlkaneId { block: 2, tx: 203 };
+        let chips = AlkaneId { block: 2, tx: 1 };
+        let id = position_id_of(&escrow.record_deposit(frank, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap());
+
+        let out = escrow.debit_ft(frank, id, chips, 120).unwrap();
+
+        assert_eq!(fields(&out.alkanes), vec![(2, 1, 120)]);
+        assert_eq!(ft_balance(frank, chips), 380);
+        assert_eq!(fields(&escrow.position_parcel(id).unwrap()), vec![(2, 1, 380)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn ft_over_withdrawal_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let grace = AlkaneId { block: 2, tx: 204 };
+        let chips = AlkaneId { block: 2, tx: 1 };
+        let id = position_id_of(&escrow.record_deposit(grace, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap());
+
+        let err = escrow.debit_ft(grace, id, chips, 501).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InsufficientPositionBalance));
+        assert_eq!(ft_balance(grace, chips), 500);
+        assert_eq!(fields(&escrow.position_parcel(id).unwrap()), vec![(2, 1, 500)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn position_still_redeems_its_nft() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let ivan = AlkaneId { block: 2, tx: 206 };
+        let resp = escrow.record_deposit(ivan, AlkaneTransferParcel(vec![transfer(2, 77, 1)])).unwrap();
+
+        let out = escrow
+            .redeem_partial(AlkaneTransferParcel(vec![resp.alkanes.0[0].clone()]), AlkaneId { block: 2, tx: 77 }, 1)
+            .unwrap();
+
+        assert_eq!(fields(&out.alkanes), vec![(2, 77, 1)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn nft_redeems_after_another_deposit_of_the_same_id() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let judy = AlkaneId { block: 2, tx: 209 };
+        let mallory = AlkaneId { block: 2, tx: 210 };
+        let nft = AlkaneId { block: 2, tx: 78 };
+        let resp = escrow.record_deposit(judy, AlkaneTransferParcel(vec![transfer(2, 78, 1)])).unwrap();
+        escrow.record_deposit(mallory, AlkaneTransferParcel(vec![transfer(2, 78, 1)])).unwrap();
+
+        let out = escrow.redeem_partial(AlkaneTransferParcel(vec![resp.alkanes.0[0].clone()]), nft, 1).unwrap();
+
+        assert_eq!(fields(&out.alkanes), vec![(2, 78, 1)]);
+        assert_eq!(nft_owner_pointer(&nft).get().as_ref().clone(), Vec::<u8>::from(mallory));
+    }
+
+    #[wasm_bindgen_test]
+    fn redeemed_nft_clears_its_ownership_record() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let nft = AlkaneId { block: 2, tx: 79 };
+        let resp = escrow.record_deposit(AlkaneId { block: 2, tx: 211 }, AlkaneTransferParcel(vec![transfer(2, 79, 1)])).unwrap();
+
+        escrow.redeem_partial(AlkaneTransferParcel(vec![resp.alkanes.0[0].clone()]), nft, 1).unwrap();
+
+        assert!(nft_owner_pointer(&nft).get().as_ref().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn ft_position_debits_ledger_despite_own_nft_deposit() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let oscar = AlkaneId { block: 2, tx: 212 };
+        let token = AlkaneId { block: 2, tx: 80 };
+        let ft_position = escrow.record_deposit(oscar, AlkaneTransferParcel(vec![transfer(2, 80, 5)])).unwrap();
+        escrow.record_deposit(oscar, AlkaneTransferParcel(vec![transfer(2, 80, 1)])).unwrap();
+
+        escrow.redeem_partial(AlkaneTransferParcel(vec![ft_position.alkanes.0[0].clone()]), token, 5).unwrap();
+
+        assert_eq!(ft_balance(oscar, token), 0);
+        let err = escrow.debit_ft(oscar, position_id_of(&ft_position), token, 5).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InsufficientPositionBalance));
+    }
+
+    #[wasm_bindgen_test]
+    fn deposit_repeating_an_nft_id_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+
+        let err = escrow
+            .record_deposit(AlkaneId { block: 2, tx: 213 }, AlkaneTransferParcel(vec![transfer(2, 81, 1), transfer(2, 81, 5)]))
+            .unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InvalidDeposit));
+        assert_eq!(position_count_pointer().get_value::<u128>(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn position_cannot_redeem_tokens_already_withdrawn() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let heidi = AlkaneId { block: 2, tx: 205 };
+        let chips = AlkaneId { block: 2, tx: 1 };
+        let resp = escrow.record_deposit(heidi, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+        escrow.debit_ft(heidi, position_id_of(&resp), chips, 500).unwrap();
+
+        let err = escrow
+            .redeem_partial(AlkaneTransferParcel(vec![resp.alkanes.0[0].clone()]), chips, 1)
+            .unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InsufficientPositionBalance));
+        assert!(escrow.position_parcel(position_id_of(&resp)).unwrap().0.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn sold_position_redeems_what_the_depositor_left() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let peggy = AlkaneId { block: 2, tx: 214 };
+        let chips = AlkaneId { block: 2, tx: 1 };
+        let resp = escrow.record_deposit(peggy, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap();
+        let id = position_id_of(&resp);
+        // Peggy sells the Position Token, then withdraws through the ledger.
+        let sold = resp.alkanes.0[0].clone();
+        escrow.debit_ft(peggy, id, chips, 200).unwrap();
+
+        assert_eq!(fields(&escrow.position_parcel(id).unwrap()), vec![(2, 1, 300)]);
+        let out = escrow.redeem_partial(AlkaneTransferParcel(vec![sold.clone()]), chips, 300).unwrap();
+        assert_eq!(fields(&out.alkanes), vec![(2, 1, 300)]);
+        assert_eq!(ft_balance(peggy, chips), 0);
+
+        let err = escrow.redeem_partial(AlkaneTransferParcel(vec![sold]), chips, 1).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InsufficientPositionBalance));
+    }
+
+    #[wasm_bindgen_test]
+    fn ft_withdrawal_from_anothers_position_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let chips = AlkaneId { block: 2, tx: 1 };
+        let id = position_id_of(
+            &escrow.record_deposit(AlkaneId { block: 2, tx: 215 }, AlkaneTransferParcel(vec![transfer(2, 1, 500)])).unwrap(),
+        );
+
+        let err = escrow.debit_ft(AlkaneId { block: 2, tx: 216 }, id, chips, 1).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::Unauthorized));
+        assert_eq!(fields(&escrow.position_parcel(id).unwrap()), vec![(2, 1, 500)]);
+    }
+
+    /// Escrow id handed to `store_position_token_impl` in tests.
+    const ESCROW_ID: AlkaneId = AlkaneId { block: 2, tx: 0xe5c };
+
+    #[wasm_bindgen_test]
+    fn deposit_mints_from_configured_impl() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+        let template = AlkaneId { block: 4, tx: 0x900 };
+
+        escrow.store_position_token_impl(ESCROW_ID, template).unwrap();
+        let resp = escrow.record_deposit(AlkaneId { block: 2, tx: 207 }, AlkaneTransferParcel(vec![transfer(2, 1, 5)])).unwrap();
+
+        assert_eq!(escrow.position_token_implementation().unwrap(), template);
+        let token = AlkaneId { block: 2, tx: template.tx + 1 + position_id_of(&resp) };
+        assert_eq!(fields(&resp.alkanes), vec![(token.block, token.tx, 1)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn deposit_without_impl_is_rejected() {
+        reset_test_storage();
+        position_token_implementation_pointer().set(Arc::new(Vec::new()));
+        let escrow = GameEscrow::default();
+
+        let err = escrow.record_deposit(AlkaneId { block: 2, tx: 208 }, AlkaneTransferParcel(vec![transfer(2, 1, 5)])).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::ImplNotSet));
+        assert_eq!(position_count_pointer().get_value::<u128>(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn escrow_cannot_be_its_own_impl() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+
+        let err = escrow.store_position_token_impl(ESCROW_ID, ESCROW_ID).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::ImplIsSelf));
+        assert_eq!(escrow.position_token_implementation().unwrap(), STUB_IMPL);
+    }
+
+    #[wasm_bindgen_test]
+    fn impl_outside_the_template_block_is_rejected() {
+        reset_test_storage();
+        let escrow = GameEscrow::default();
+
+        let err = escrow.store_position_token_impl(ESCROW_ID, AlkaneId { block: 2, tx: 0x900 }).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::ImplNotTemplate));
+        assert_eq!(escrow.position_token_implementation().unwrap(), STUB_IMPL);
+    }
+}
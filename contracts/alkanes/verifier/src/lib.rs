@@ -7,30 +7,52 @@
 //!
 //! Payload format in witness (index 0):
 //! - Bytes: "SATC" (magic)
-//! - u8 version (currently 1)
+//! - u8 version: 1 = plain, 2 = the rest of the payload is gzip-compressed,
+//!   3 = plain with the old root appended, 4 = version 3 gzip-compressed;
+//!   updates and VerifyOnly accept only 3 and 4
 //! - u8 preprocessed variant: 0 = Canonical, 1 = CanonicalWithoutPedersen
 //! - u32 be: number of field elements N
 //! - N elements of 32 bytes each: big-endian starknet_ff::FieldElement
 //! - u32 be: length L of new_root bytes
 //! - L bytes: new_root (expected 32 bytes)
+//! - versions 3 and 4 only: u32 be length O, then O bytes of the root the
+//!   block was proven from, which must match `/state_root` once one is stored
+//!
+//! Large payloads may instead be split across inputs 0..total, each envelope
+//! holding a chunk: "SATM", u16 be total, u16 be index, then the next slice.
+//!
+//! Inclusion witness (index 0) for VerifyInclusion:
+//! - Bytes: "SATI" (magic)
+//! - u8 version (currently 1)
+//! - 32 bytes: leaf hash, as the sequencer's `hash_player` / `hash_nft`
+//! - u64 be: leaf index
+//! - u64 be: total leaf count
+//! - u32 be: length P of the proof
+//! - P bytes: serialized rs_merkle `MerkleProof<Sha256>`
 //!
 //! See ESSENTIAL_ALKANES_CONTRACTS_CHEATSHEET.md (Rule 27) for witness reading.
 
+pub mod payload;
+
 use alkanes_runtime::{
     auth::AuthenticatedResponder, declare_alkane, message::MessageDispatch, runtime::AlkaneResponder, storage::StoragePointer,
 };
 use alkanes_support::{context::Context, id::AlkaneId, response::CallResponse, witness::find_witness_payload};
-use anyhow::{anyhow, Result};
-use cairo_air_verifier_lite::verifier::verify_cairo;
-use cairo_air_verifier_lite::{air::CairoProof, PreProcessedTraceVariant};
+use anyhow::Result;
+use error_codes::ErrorCode;
+#[cfg(not(test))]
+use cairo_air_verifier_lite::{air::CairoProof, verifier::verify_cairo};
+use cairo_air_verifier_lite::PreProcessedTraceVariant;
 use starknet_ff::FieldElement;
 use bitcoin::hashes::Hash;
 use bitcoin::{Transaction, Txid};
+use rs_merkle::{algorithms::Sha256, MerkleProof};
 use metashrew_support::compat::to_arraybuffer_layout;
 use metashrew_support::index_pointer::KeyValuePointer;
 use metashrew_support::utils::consensus_decode;
 use std::io::Cursor;
 use std::sync::Arc;
+#[cfg(not(test))]
 use stwo::core::vcs::blake2_merkle::{Blake2sMerkleChannel, Blake2sMerkleHasher};
 
 pub struct ContextHandle(());
@@ -54,6 +76,20 @@ pub const CONTEXT: ContextHandle = ContextHandle(());
 trait ContextExt {
     /// Get the transaction ID from the context
     fn transaction_id(&self) -> Result<Txid>;
+
+    /// `transaction_id`, or the all-zero Txid if the transaction bytes don't
+    /// decode, so a verified proof is never reverted over its event key.
+    fn transaction_id_or_zero(&self) -> Txid {
+        self.transaction_id().unwrap_or_else(|_| Txid::all_zeros())
+    }
+}
+
+/// Consensus-decodes `bytes` and returns the txid, naming the input length on failure.
+fn txid_from_bytes(bytes: Vec<u8>) -> Result<Txid> {
+    let len = bytes.len();
+    consensus_decode::<Transaction>(&mut Cursor::new(bytes))
+        .map(|tx| tx.compute_txid())
+        .map_err(|e| ErrorCode::TxidDecodeFailed.with(format!("{len} bytes: {e}")).into())
 }
 
 #[cfg(test)]
@@ -70,13 +106,66 @@ impl ContextExt for Context {
 #[cfg(not(test))]
 impl ContextExt for Context {
     fn transaction_id(&self) -> Result<Txid> {
-        Ok(
-            consensus_decode::<Transaction>(&mut std::io::Cursor::new(CONTEXT.transaction()))?
-                .compute_txid(),
-        )
+        txid_from_bytes(CONTEXT.transaction())
     }
 }
 
+/// Checks a deserialized-felt Cairo proof for the given preprocessed variant.
+trait ProofCheck {
+    fn check_proof(&self, felts: &[FieldElement], variant: PreProcessedTraceVariant) -> Result<()>;
+}
+
+#[cfg(not(test))]
+impl ProofCheck for Verifier {
+    fn check_proof(&self, felts: &[FieldElement], variant: PreProcessedTraceVariant) -> Result<()> {
+        use stwo_cairo_serialize::CairoDeserialize;
+        let proof: CairoProof<Blake2sMerkleHasher> = CairoProof::deserialize(&mut felts.iter());
+        verify_cairo::<Blake2sMerkleChannel>(proof, variant)
+            .map_err(|e| ErrorCode::VerificationFailed.with(e).into())
+    }
+}
+
+#[cfg(test)]
+impl ProofCheck for Verifier {
+    fn check_proof(&self, felts: &[FieldElement], _variant: PreProcessedTraceVariant) -> Result<()> {
+        // Stand-in for the stwo verifier: accepts only the one-felt proof `[7]`.
+        if felts == [FieldElement::from(7u64)] {
+            Ok(())
+        } else {
+            Err(ErrorCode::VerificationFailed.with("stub proof rejected").into())
+        }
+    }
+}
+
+/// Parses `bytes` and runs its proof through the same check VerifyAndUpdate
+/// does, without touching storage; lets off-chain provers test against it.
+pub fn check_payload(bytes: &[u8]) -> Result<payload::Payload> {
+    let parsed = payload::decode_payload(bytes)?;
+    Verifier::default().check_proof(&parsed.felts, parsed.variant)?;
+    Ok(parsed)
+}
+
+/// The envelope payload carried by input `vin`'s witness alone, if any.
+fn payload_at_input(tx: &Transaction, vin: usize) -> Option<Vec<u8>> {
+    let single = Transaction {
+        version: tx.version,
+        lock_time: tx.lock_time,
+        input: vec![tx.input.get(vin)?.clone()],
+        output: Vec::new(),
+    };
+    find_witness_payload(&single, 0)
+}
+
+/// Gathers the chunks of inputs `0..total`, where `first` (input 0's) names `total`.
+fn chunked_payload(tx: &Transaction, first: Vec<u8>) -> Result<Vec<u8>> {
+    let total = payload::chunk_header(&first).map_or(0, |(total, _)| usize::from(total));
+    let mut chunks = vec![first];
+    for vin in 1..total {
+        chunks.push(payload_at_input(tx, vin).ok_or(ErrorCode::MissingChunk)?);
+    }
+    payload::reassemble_chunks(&chunks)
+}
+
 // Storage keys
 fn initialized_pointer() -> StoragePointer {
     StoragePointer::from_keyword("/initialized")
@@ -90,6 +179,18 @@ fn state_root_pointer() -> StoragePointer {
 fn last_variant_pointer() -> StoragePointer {
     StoragePointer::from_keyword("/last_preprocessed_variant")
 }
+fn schema_version_pointer() -> StoragePointer {
+    StoragePointer::from_keyword("/schema_version")
+}
+fn witness_index_pointer() -> StoragePointer {
+    StoragePointer::from_keyword("/witness_index")
+}
+fn event_count_pointer() -> StoragePointer {
+    StoragePointer::from_keyword("/event_count")
+}
+fn event_pointer(index: u128) -> StoragePointer {
+    StoragePointer::from_keyword("/events/").select(&index.to_le_bytes().to_vec())
+}
 // No witness storage key: read witness bytes from the current transaction
 
 #[derive(Default)]
@@ -107,6 +208,31 @@ pub enum VerifierMessage {
     // No inputs; reads payload from tx witness per the format documented above.
     #[opcode(1)]
     VerifyAndUpdate,
+    // Checks the Merkle inclusion witness against `/state_root`; returns [1] or [0].
+    #[opcode(3)]
+    #[returns(Vec<u8>)]
+    VerifyInclusion,
+    // Parses and verifies the proof payload like VerifyAndUpdate, without auth or
+    // touching storage; returns [1] if valid, otherwise the error string bytes.
+    #[opcode(4)]
+    #[returns(Vec<u8>)]
+    VerifyOnly,
+    // Advances `/schema_version` to `target` after a contract upgrade; forward only.
+    // Leaves the state root and bridge untouched.
+    #[opcode(5)]
+    MigrateSchema { target: u128 },
+    // Sets the input whose witness is read when input 0 carries no payload.
+    #[opcode(6)]
+    SetWitnessIndex { index: u128 },
+    // Returns the event recorded by the `index`-th successful VerifyAndUpdate:
+    // 32-byte txid, variant byte, new root. Empty if out of range.
+    #[opcode(94)]
+    #[returns(Vec<u8>)]
+    GetEvent { index: u128 },
+    // Returns the authorized bridge AlkaneId bytes, or empty if uninitialized
+    #[opcode(95)]
+    #[returns(Vec<u8>)]
+    GetBridge,
     // Returns the latest state root bytes
     #[opcode(97)]
     #[returns(Vec<u8>)]
@@ -128,6 +254,13 @@ impl Verifier {
         p.set(Arc::new(id.into()));
     }
 
+    fn bridge_bytes(&self) -> Vec<u8> {
+        if !self.is_initialized() {
+            return Vec::new();
+        }
+        bridge_pointer().get().as_ref().clone()
+    }
+
     fn set_state_root(&self, root: &[u8]) {
         state_root_pointer().set(Arc::new(root.to_vec()));
     }
@@ -138,77 +271,105 @@ impl Verifier {
 
     fn set_last_variant(&self, v: u8) { last_variant_pointer().set(Arc::new(vec![v])); }
 
+    /// Appends `txid || variant || new_root` to the `/events/<n>` log.
+    fn record_event(&self, txid: &Txid, variant: u8, new_root: &[u8]) {
+        let mut count = event_count_pointer();
+        let index = count.get_value::<u128>();
+        let mut record = txid.to_byte_array().to_vec();
+        record.push(variant);
+        record.extend_from_slice(new_root);
+        event_pointer(index).set(Arc::new(record));
+        count.set_value::<u128>(index + 1);
+    }
+
+    fn schema_version(&self) -> u128 { schema_version_pointer().get_value::<u128>() }
+
+    fn witness_index(&self) -> u128 { witness_index_pointer().get_value::<u128>() }
+
     fn read_witness_payload(&self) -> Result<Vec<u8>> {
         let tx = consensus_decode::<Transaction>(&mut Cursor::new(CONTEXT.transaction()))?;
-        let data: Vec<u8> = find_witness_payload(&tx, 0).unwrap_or_else(|| vec![]);
-        Ok(data)
-    }
-
-    fn parse_payload(
-        &self,
-        mut bytes: &[u8],
-    ) -> Result<(PreProcessedTraceVariant, Vec<FieldElement>, Vec<u8>)> {
-        // Expect magic
-        if bytes.len() < 4 {
-            return Err(anyhow!("PAYLOAD_TOO_SHORT"));
-        }
-        let magic = &bytes[0..4];
-        if magic != b"SATC" {
-            return Err(anyhow!("BAD_MAGIC"));
-        }
-        if bytes.len() < 6 {
-            return Err(anyhow!("PAYLOAD_TOO_SHORT"));
-        }
-        let version = bytes[4];
-        if version != 1 {
-            return Err(anyhow!("UNSUPPORTED_VERSION"));
-        }
-        let variant_byte = bytes[5];
-        let preprocessed_variant = match variant_byte {
-            0 => PreProcessedTraceVariant::Canonical,
-            1 => PreProcessedTraceVariant::CanonicalWithoutPedersen,
-            _ => return Err(anyhow!("UNKNOWN_VARIANT")),
+        self.witness_payload_of(&tx)
+    }
+
+    /// Envelope payload from input 0's witness, else from the input at `/witness_index`.
+    /// If input 0 holds a `SATM` chunk, the payload is reassembled from inputs `0..total`.
+    fn witness_payload_of(&self, tx: &Transaction) -> Result<Vec<u8>> {
+        let fallback = usize::try_from(self.witness_index()).ok().filter(|&i| i != 0);
+        let found = match payload_at_input(tx, 0) {
+            Some(first) if payload::chunk_header(&first).is_some() => Some(chunked_payload(tx, first)?),
+            first => first.or_else(|| fallback.and_then(|i| payload_at_input(tx, i))),
         };
-        bytes = &bytes[6..];
-        if bytes.len() < 4 {
-            return Err(anyhow!("PAYLOAD_TOO_SHORT"));
-        }
-        let n = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
-        bytes = &bytes[4..];
-        if bytes.len() < 32 * n + 4 {
-            return Err(anyhow!("PROOF_BYTES_TOO_SHORT"));
-        }
-        let mut felts: Vec<FieldElement> = Vec::with_capacity(n);
-        for i in 0..n {
-            let word = &bytes[32 * i..32 * (i + 1)];
-            let arr: [u8; 32] = word.try_into().map_err(|_| anyhow!("BAD_FELT"))?;
-            let fe = FieldElement::from_bytes_be(&arr).map_err(|_| anyhow!("BAD_FELT"))?;
-            felts.push(fe);
-        }
-        bytes = &bytes[32 * n..];
-        let l = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
-        bytes = &bytes[4..];
-        if bytes.len() < l {
-            return Err(anyhow!("ROOT_BYTES_TOO_SHORT"));
-        }
-        let root = bytes[..l].to_vec();
-        Ok((preprocessed_variant, felts, root))
-    }
-
-    fn deserialize_proof(
-        &self,
-        felts: &[FieldElement],
-    ) -> Result<CairoProof<Blake2sMerkleHasher>> {
-        use stwo_cairo_serialize::CairoDeserialize;
-        let mut it = felts.iter();
-        let proof: CairoProof<Blake2sMerkleHasher> = CairoProof::deserialize(&mut it);
-        Ok(proof)
+        match found {
+            None => Err(ErrorCode::NoWitnessPayload.into()),
+            Some(data) if data.is_empty() => Err(ErrorCode::EmptyWitnessPayload.into()),
+            Some(data) => Ok(data),
+        }
+    }
+
+    fn parse_payload(&self, bytes: &[u8]) -> Result<payload::Payload> {
+        payload::decode_payload(bytes)
+    }
+
+    fn check_inclusion(&self, mut bytes: &[u8]) -> Result<bool> {
+        if bytes.len() < 5 + 32 + 8 + 8 + 4 {
+            return Err(ErrorCode::PayloadTooShort.into());
+        }
+        if &bytes[0..4] != b"SATI" {
+            return Err(ErrorCode::BadMagic.into());
+        }
+        if bytes[4] != 1 {
+            return Err(ErrorCode::UnsupportedVersion.into());
+        }
+        bytes = &bytes[5..];
+        let leaf: [u8; 32] = bytes[..32].try_into().map_err(|_| ErrorCode::BadLeaf)?;
+        // usize is 32 bits on wasm32: refuse what would otherwise truncate.
+        let index = usize::try_from(u64::from_be_bytes(bytes[32..40].try_into().unwrap()))
+            .map_err(|_| ErrorCode::BadLeafIndex)?;
+        let total = usize::try_from(u64::from_be_bytes(bytes[40..48].try_into().unwrap()))
+            .map_err(|_| ErrorCode::BadLeafIndex)?;
+        let p = u32::from_be_bytes(bytes[48..52].try_into().unwrap()) as usize;
+        bytes = &bytes[52..];
+        if bytes.len() < p {
+            return Err(ErrorCode::ProofBytesTooShort.into());
+        }
+        let root: [u8; 32] = self
+            .state_root_bytes()
+            .try_into()
+            .map_err(|_| ErrorCode::NoStateRoot)?;
+        if index >= total {
+            return Ok(false);
+        }
+        Ok(match MerkleProof::<Sha256>::from_bytes(&bytes[..p]) {
+            Ok(proof) => proof.verify(root, &[index], &[leaf], total),
+            Err(_) => false,
+        })
+    }
+
+    /// Parses `payload` and verifies its proof; returns the variant and claimed new root.
+    /// The payload must name its old root, and that must be the stored root once one is.
+    fn verify_payload(&self, payload: &[u8]) -> Result<(PreProcessedTraceVariant, Vec<u8>)> {
+        let parsed = self.parse_payload(payload)?;
+        let old_root = parsed.old_root.as_ref().ok_or(ErrorCode::MissingOldRoot)?;
+        let stored = self.state_root_bytes();
+        if !stored.is_empty() && stored != *old_root {
+            return Err(ErrorCode::StaleRoot.with(format!("payload proves from 0x{}", hex::encode(old_root))).into());
+        }
+        self.check_proof(&parsed.felts, parsed.variant)?;
+        Ok((parsed.variant, parsed.new_root))
+    }
+
+    /// `[1]` if `payload` verifies, else the error string bytes.
+    fn dry_run(&self, payload: &[u8]) -> Vec<u8> {
+        match self.verify_payload(payload) {
+            Ok(_) => vec![1],
+            Err(e) => e.to_string().into_bytes(),
+        }
     }
 
     fn initialize(&self, bridge: AlkaneId) -> Result<CallResponse> {
         self.only_owner()?;
         if self.is_initialized() {
-            return Err(anyhow!("ALREADY_INITIALIZED"));
+            return Err(ErrorCode::AlreadyInitialized.into());
         }
         self.observe_initialization()?;
         self.set_bridge(bridge);
@@ -221,23 +382,77 @@ impl Verifier {
         self.only_owner()?;
 
         let payload = self.read_witness_payload()?;
-        let (variant, felts, new_root) = self.parse_payload(&payload)?;
-        let proof = self.deserialize_proof(&felts)?;
+        let txid = self.context()?.transaction_id_or_zero();
+        self.apply_verified(&txid, &payload)?;
 
-        // Verify
-        verify_cairo::<Blake2sMerkleChannel>(proof, variant)
-            .map_err(|e| anyhow!(format!("VERIFICATION_FAILED: {e}")))?;
+        Ok(CallResponse::default())
+    }
+
+    /// Verifies `payload` and, if valid, stores its root and logs the update under `txid`.
+    fn apply_verified(&self, txid: &Txid, payload: &[u8]) -> Result<()> {
+        let (variant, new_root) = self.verify_payload(payload)?;
+        let variant = payload::variant_byte(&variant);
 
         // Update storage
         self.set_state_root(&new_root);
-        self.set_last_variant(match variant {
-            PreProcessedTraceVariant::Canonical => 0,
-            PreProcessedTraceVariant::CanonicalWithoutPedersen => 1,
-        });
+        self.set_last_variant(variant);
+        self.record_event(txid, variant, &new_root);
+        Ok(())
+    }
 
+    fn verify_only(&self) -> Result<CallResponse> {
+        let mut resp = CallResponse::default();
+        resp.data = match self.read_witness_payload() {
+            Ok(payload) => self.dry_run(&payload),
+            Err(e) => e.to_string().into_bytes(),
+        };
+        Ok(resp)
+    }
+
+    fn verify_inclusion(&self) -> Result<CallResponse> {
+        let payload = self.read_witness_payload()?;
+        let mut resp = CallResponse::default();
+        resp.data = vec![self.check_inclusion(&payload)? as u8];
+        Ok(resp)
+    }
+
+    fn migrate_schema(&self, target: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        self.advance_schema_version(target)
+    }
+
+    fn set_witness_index(&self, index: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        witness_index_pointer().set_value::<u128>(index);
+        Ok(CallResponse::default())
+    }
+
+    fn advance_schema_version(&self, target: u128) -> Result<CallResponse> {
+        let current = self.schema_version();
+        if target < current {
+            return Err(ErrorCode::Downgrade.into());
+        }
+        if target == current {
+            return Err(ErrorCode::AlreadyAtVersion.into());
+        }
+        schema_version_pointer().set_value::<u128>(target);
         Ok(CallResponse::default())
     }
 
+    fn get_event(&self, index: u128) -> Result<CallResponse> {
+        let mut resp = CallResponse::default();
+        if index < event_count_pointer().get_value::<u128>() {
+            resp.data = event_pointer(index).get().as_ref().clone();
+        }
+        Ok(resp)
+    }
+
+    fn get_bridge(&self) -> Result<CallResponse> {
+        let mut resp = CallResponse::default();
+        resp.data = self.bridge_bytes();
+        Ok(resp)
+    }
+
     fn get_state_root(&self) -> Result<CallResponse> {
         let mut resp = CallResponse::default();
         resp.data = self.state_root_bytes();
@@ -249,4 +464,407 @@ declare_alkane! {
     impl AlkaneResponder for Verifier { type Message = VerifierMessage; }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::payload::variant_byte;
+    use flate2::{write::GzEncoder, Compression};
+    use rs_merkle::{Hasher, MerkleTree};
+    use metashrew_support::utils::consume_u128;
+    use std::io::Write;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// Variant byte, one felt, 32-byte root: the part after the version byte.
+    fn payload_body() -> Vec<u8> {
+        let mut body = vec![1u8];
+        body.extend_from_slice(&1u32.to_be_bytes());
+        let mut felt = [0u8; 32];
+        felt[31] = 7;
+        body.extend_from_slice(&felt);
+        body.extend_from_slice(&32u32.to_be_bytes());
+        body.extend_from_slice(&[0x5a; 32]);
+        body
+    }
+
+    /// `payload_body` followed by a 32-byte old root: a version 3 / 4 body.
+    fn roots_body(old_root: [u8; 32]) -> Vec<u8> {
+        let mut body = payload_body();
+        body.extend_from_slice(&32u32.to_be_bytes());
+        body.extend_from_slice(&old_root);
+        body
+    }
+
+    fn payload(version: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = b"SATC".to_vec();
+        bytes.push(version);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn reset_test_storage() {
+        for k in [
+            "/initialized",
+            "/bridge_id",
+            "/state_root",
+            "/last_preprocessed_variant",
+            "/schema_version",
+            "/event_count",
+            "/witness_index",
+        ] {
+            StoragePointer::from_keyword(k).set(Arc::new(Vec::new()));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn get_bridge_returns_initialized_bridge() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        let bridge = AlkaneId { block: 2, tx: 0x3e8 };
+        // What Initialize stores once owner auth has passed.
+        verifier.set_bridge(bridge);
+        verifier.set_initialized();
+
+        let mut cursor = Cursor::new(verifier.get_bridge().unwrap().data);
+        let decoded = AlkaneId::new(consume_u128(&mut cursor).unwrap(), consume_u128(&mut cursor).unwrap());
+        assert_eq!(decoded, bridge);
+    }
+
+    #[wasm_bindgen_test]
+    fn get_bridge_is_empty_before_initialize() {
+        reset_test_storage();
+        assert!(Verifier::default().get_bridge().unwrap().data.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn migrate_schema_moves_forward_only() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        verifier.set_state_root(&[7u8; 32]);
+        verifier.set_bridge(AlkaneId { block: 2, tx: 1 });
+        let bridge = bridge_pointer().get().as_ref().clone();
+
+        verifier.advance_schema_version(2).unwrap();
+        assert_eq!(verifier.schema_version(), 2);
+        assert_eq!(verifier.state_root_bytes(), vec![7u8; 32]);
+        assert_eq!(bridge_pointer().get().as_ref().clone(), bridge);
+    }
+
+    #[wasm_bindgen_test]
+    fn migrate_schema_rejects_downgrade() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        verifier.advance_schema_version(3).unwrap();
+
+        let err = verifier.advance_schema_version(2).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::Downgrade));
+        assert_eq!(verifier.schema_version(), 3);
+    }
+
+    #[wasm_bindgen_test]
+    fn migrate_schema_rejects_repeat() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        verifier.advance_schema_version(1).unwrap();
+
+        let err = verifier.advance_schema_version(1).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::AlreadyAtVersion));
+    }
+
+    #[wasm_bindgen_test]
+    fn compressed_payload_parses_like_plain() {
+        let verifier = Verifier::default();
+        let plain = verifier.parse_payload(&payload(1, &payload_body())).unwrap();
+        let gz = verifier.parse_payload(&payload(2, &gzip(&payload_body()))).unwrap();
+
+        assert_eq!(variant_byte(&gz.variant), variant_byte(&plain.variant));
+        assert_eq!(gz.felts, plain.felts);
+        assert_eq!(gz.new_root, plain.new_root);
+        assert_eq!(plain.felts, vec![FieldElement::from(7u64)]);
+        assert_eq!(plain.new_root, vec![0x5a; 32]);
+    }
+
+    #[wasm_bindgen_test]
+    fn corrupt_compressed_payload_is_rejected() {
+        let err = Verifier::default().parse_payload(&payload(2, b"not gzip")).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::BadCompression));
+    }
+
+    #[wasm_bindgen_test]
+    fn verify_only_accepts_valid_proof() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        verifier.set_state_root(&[3u8; 32]);
+
+        assert_eq!(verifier.dry_run(&payload(3, &roots_body([3u8; 32]))), vec![1]);
+        assert_eq!(verifier.state_root_bytes(), vec![3u8; 32]);
+    }
+
+    #[wasm_bindgen_test]
+    fn roots_payload_must_prove_from_the_stored_root() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        verifier.set_state_root(&[3u8; 32]);
+
+        assert_eq!(verifier.dry_run(&payload(payload::ROOTS_VERSION, &roots_body([3u8; 32]))), vec![1]);
+        assert_eq!(verifier.dry_run(&payload(payload::COMPRESSED_ROOTS_VERSION, &gzip(&roots_body([3u8; 32])))), vec![1]);
+        let err = verifier.verify_payload(&payload(payload::ROOTS_VERSION, &roots_body([4u8; 32]))).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::StaleRoot));
+        assert_eq!(verifier.state_root_bytes(), vec![3u8; 32]);
+    }
+
+    #[wasm_bindgen_test]
+    fn payload_without_an_old_root_is_rejected() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        for bytes in [payload(1, &payload_body()), payload(2, &gzip(&payload_body()))] {
+            let err = verifier.verify_payload(&bytes).unwrap_err();
+            assert_eq!(ErrorCode::of(&err), Some(ErrorCode::MissingOldRoot));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn verify_only_reports_invalid_proof_without_storing() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        verifier.set_state_root(&[3u8; 32]);
+        let mut body = roots_body([3u8; 32]);
+        body[5 + 31] = 8; // the single felt: 7 -> 8
+
+        let out = verifier.dry_run(&payload(3, &body));
+
+        assert_eq!(ErrorCode::parse(&out), Some(ErrorCode::VerificationFailed));
+        assert_eq!(verifier.state_root_bytes(), vec![3u8; 32]);
+        assert!(last_variant_pointer().get().as_ref().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn failures_carry_stable_error_codes() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        let mut bad_magic = payload(1, &payload_body());
+        bad_magic[..4].copy_from_slice(b"XXXX");
+        let mut bad_proof = roots_body([0u8; 32]);
+        bad_proof[5 + 31] = 8;
+
+        let cases = [
+            (bad_magic, ErrorCode::BadMagic, 1),
+            (payload(9, &payload_body()), ErrorCode::UnsupportedVersion, 2),
+            (payload(3, &bad_proof), ErrorCode::VerificationFailed, 3),
+        ];
+        for (bytes, code, number) in cases {
+            assert_eq!(code.code(), number);
+            assert_eq!(ErrorCode::of(&verifier.verify_payload(&bytes).unwrap_err()), Some(code));
+            assert_eq!(ErrorCode::parse(&verifier.dry_run(&bytes)), Some(code));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn verified_update_is_logged_as_event() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        let txid = Context::default().transaction_id().unwrap();
+
+        verifier.apply_verified(&txid, &payload(3, &roots_body([0u8; 32]))).unwrap();
+
+        let event = verifier.get_event(0).unwrap().data;
+        assert_eq!(&event[..32], txid.to_byte_array().as_slice());
+        assert_eq!(event[32], 1); // payload_body's variant
+        assert_eq!(&event[33..], verifier.state_root_bytes().as_slice());
+        assert_eq!(&event[33..], [0x5a; 32].as_slice());
+        assert!(verifier.get_event(1).unwrap().data.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn rejected_proof_logs_no_event() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        let mut body = roots_body([0u8; 32]);
+        body[5 + 31] = 8;
+
+        assert!(verifier.apply_verified(&Txid::all_zeros(), &payload(3, &body)).is_err());
+        assert_eq!(event_count_pointer().get_value::<u128>(), 0);
+        assert!(verifier.get_event(0).unwrap().data.is_empty());
+    }
 
+    /// `OP_FALSE OP_IF "BIN" <> <payload> OP_ENDIF`, as the sequencer builds it.
+    fn envelope_input(payload: &[u8]) -> bitcoin::TxIn {
+        use bitcoin::opcodes::{all::{OP_ENDIF, OP_IF}, OP_FALSE};
+        use bitcoin::script::{Builder, PushBytesBuf};
+        let mut builder = Builder::new()
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(PushBytesBuf::try_from(b"BIN".to_vec()).unwrap())
+            .push_slice(PushBytesBuf::new());
+        for chunk in payload.chunks(520) {
+            builder = builder.push_slice(PushBytesBuf::try_from(chunk.to_vec()).unwrap());
+        }
+        let script = builder.push_opcode(OP_ENDIF).into_script();
+        let mut control_block = vec![0xc0];
+        control_block.extend_from_slice(&[0u8; 32]);
+        bitcoin::TxIn {
+            witness: bitcoin::Witness::from_slice(&[script.into_bytes(), control_block]),
+            ..Default::default()
+        }
+    }
+
+    fn tx_with_inputs(input: Vec<bitcoin::TxIn>) -> Transaction {
+        Transaction { input, ..sample_tx() }
+    }
+
+    #[wasm_bindgen_test]
+    fn missing_witness_payload_is_named() {
+        reset_test_storage();
+        let tx = tx_with_inputs(vec![bitcoin::TxIn::default()]);
+
+        let err = Verifier::default().witness_payload_of(&tx).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NoWitnessPayload));
+    }
+
+    #[wasm_bindgen_test]
+    fn empty_witness_payload_is_distinguished() {
+        reset_test_storage();
+        let tx = tx_with_inputs(vec![envelope_input(&[])]);
+
+        let err = Verifier::default().witness_payload_of(&tx).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::EmptyWitnessPayload));
+    }
+
+    #[wasm_bindgen_test]
+    fn payload_at_configured_index_is_found() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        let body = payload(1, &payload_body());
+        let tx = tx_with_inputs(vec![bitcoin::TxIn::default(), envelope_input(&body)]);
+
+        assert_eq!(ErrorCode::of(&verifier.witness_payload_of(&tx).unwrap_err()), Some(ErrorCode::NoWitnessPayload));
+        witness_index_pointer().set_value::<u128>(1);
+        assert_eq!(verifier.witness_payload_of(&tx).unwrap(), body);
+    }
+
+    #[wasm_bindgen_test]
+    fn chunked_payload_reassembles_across_inputs() {
+        reset_test_storage();
+        let verifier = Verifier::default();
+        let body = payload(3, &roots_body([0u8; 32]));
+        let chunks = payload::split_payload(&body, body.len() / 3 + 1).unwrap();
+        assert_eq!(chunks.len(), 3);
+        let tx = tx_with_inputs(chunks.iter().map(|c| envelope_input(c)).collect());
+
+        let reassembled = verifier.witness_payload_of(&tx).unwrap();
+
+        assert_eq!(reassembled, body);
+        assert_eq!(verifier.dry_run(&reassembled), vec![1]);
+    }
+
+    #[wasm_bindgen_test]
+    fn chunked_payload_missing_a_chunk_is_rejected() {
+        reset_test_storage();
+        let chunks = payload::split_payload(&payload(1, &payload_body()), 16).unwrap();
+        let tx = tx_with_inputs(vec![envelope_input(&chunks[0]), envelope_input(&chunks[1])]);
+
+        let err = Verifier::default().witness_payload_of(&tx).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::MissingChunk));
+    }
+
+    /// Context whose transaction bytes are a fixed fixture.
+    struct FixtureContext(Vec<u8>);
+
+    impl ContextExt for FixtureContext {
+        fn transaction_id(&self) -> Result<Txid> {
+            txid_from_bytes(self.0.clone())
+        }
+    }
+
+    fn sample_tx() -> Transaction {
+        Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(546),
+                script_pubkey: bitcoin::ScriptBuf::new(),
+            }],
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn txid_decodes_valid_transaction() {
+        let tx = sample_tx();
+        let ctx = FixtureContext(bitcoin::consensus::serialize(&tx));
+
+        assert_eq!(ctx.transaction_id().unwrap(), tx.compute_txid());
+        assert_eq!(ctx.transaction_id_or_zero(), tx.compute_txid());
+    }
+
+    #[wasm_bindgen_test]
+    fn txid_decode_failure_names_length() {
+        let err = FixtureContext(Vec::new()).transaction_id().unwrap_err();
+        assert!(err.to_string().starts_with("[E0016] TXID_DECODE_FAILED: 0 bytes"));
+
+        let truncated = bitcoin::consensus::serialize(&sample_tx())[..6].to_vec();
+        let err = FixtureContext(truncated).transaction_id().unwrap_err();
+        assert!(err.to_string().starts_with("[E0016] TXID_DECODE_FAILED: 6 bytes"));
+    }
+
+    #[wasm_bindgen_test]
+    fn txid_or_zero_falls_back_on_decode_failure() {
+        assert_eq!(FixtureContext(vec![0x02, 0x00]).transaction_id_or_zero(), Txid::all_zeros());
+        assert_eq!(Context::default().transaction_id_or_zero(), Txid::all_zeros());
+    }
+
+    fn inclusion_witness(leaf: [u8; 32], index: u64, total: u64, proof: &[u8]) -> Vec<u8> {
+        let mut bytes = b"SATI".to_vec();
+        bytes.push(1);
+        bytes.extend_from_slice(&leaf);
+        bytes.extend_from_slice(&index.to_be_bytes());
+        bytes.extend_from_slice(&total.to_be_bytes());
+        bytes.extend_from_slice(&(proof.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(proof);
+        bytes
+    }
+
+    /// Four leaves with the committed root stored as `/state_root`.
+    fn rooted_tree() -> (Vec<[u8; 32]>, MerkleTree<Sha256>) {
+        reset_test_storage();
+        let leaves: Vec<[u8; 32]> = (0u8..4).map(|i| Sha256::hash(&[i])).collect();
+        let tree = MerkleTree::<Sha256>::from_leaves(&leaves);
+        Verifier::default().set_state_root(&tree.root().unwrap());
+        (leaves, tree)
+    }
+
+    #[wasm_bindgen_test]
+    fn included_leaf_verifies() {
+        let (leaves, tree) = rooted_tree();
+        let proof = tree.proof(&[2]).to_bytes();
+
+        assert!(Verifier::default().check_inclusion(&inclusion_witness(leaves[2], 2, 4, &proof)).unwrap());
+    }
+
+    #[wasm_bindgen_test]
+    fn non_included_leaf_is_rejected() {
+        let (_, tree) = rooted_tree();
+        let proof = tree.proof(&[2]).to_bytes();
+        let stranger = Sha256::hash(b"not a leaf");
+
+        assert!(!Verifier::default().check_inclusion(&inclusion_witness(stranger, 2, 4, &proof)).unwrap());
+    }
+
+    /// Truncated to 32 bits these would read as index 2 of 4 and verify.
+    #[cfg(target_pointer_width = "32")]
+    #[wasm_bindgen_test]
+    fn leaf_index_past_usize_is_rejected() {
+        let (leaves, tree) = rooted_tree();
+        let proof = tree.proof(&[2]).to_bytes();
+
+        let err = Verifier::default()
+            .check_inclusion(&inclusion_witness(leaves[2], (1 << 32) + 2, (1 << 32) + 4, &proof))
+            .unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::BadLeafIndex));
+    }
+}
@@ -0,0 +1,308 @@
+//! SATC proof payload encoding, shared by the contract and its clients.
+//!
+//! See the crate docs for the byte layout. `build_payload` emits the plain
+//! (`VERSION`) form and `build_roots_payload` the `ROOTS_VERSION` one;
+//! `decode_payload` accepts those and their gzipped forms.
+
+use anyhow::Result;
+use error_codes::ErrorCode;
+use cairo_air_verifier_lite::PreProcessedTraceVariant;
+use flate2::read::GzDecoder;
+use starknet_ff::FieldElement;
+use std::io::Read;
+
+pub const MAGIC: &[u8; 4] = b"SATC";
+/// Plain payload version emitted by `build_payload`.
+pub const VERSION: u8 = 1;
+/// Same layout as `VERSION` with everything after the version byte gzipped.
+pub const COMPRESSED_VERSION: u8 = 2;
+/// `VERSION` followed by u32 BE old-root length and the root the block was proven from.
+pub const ROOTS_VERSION: u8 = 3;
+/// Same layout as `ROOTS_VERSION` with everything after the version byte gzipped.
+pub const COMPRESSED_ROOTS_VERSION: u8 = 4;
+
+/// Magic of one chunk of a payload split across several inputs' envelopes.
+pub const CHUNK_MAGIC: &[u8; 4] = b"SATM";
+/// `CHUNK_MAGIC`, u16 BE chunk count, u16 BE chunk index.
+pub const CHUNK_HEADER_LEN: usize = 8;
+
+/// Upper bound on a decompressed (version 2 or 4) payload, against gzip bombs.
+pub const MAX_INFLATED_PAYLOAD: usize = 8 * 1024 * 1024;
+
+/// Most proof felts a payload may declare: as many as fit in an inflated payload.
+pub const MAX_FELTS: usize = MAX_INFLATED_PAYLOAD / 32;
+
+pub fn variant_byte(variant: &PreProcessedTraceVariant) -> u8 {
+    match variant {
+        PreProcessedTraceVariant::Canonical => 0,
+        PreProcessedTraceVariant::CanonicalWithoutPedersen => 1,
+    }
+}
+
+/// A decoded SATC payload. `old_root` is only carried by `ROOTS_VERSION`.
+pub struct Payload {
+    pub variant:  PreProcessedTraceVariant,
+    pub felts:    Vec<FieldElement>,
+    pub new_root: Vec<u8>,
+    pub old_root: Option<Vec<u8>>,
+}
+
+/// Encodes a plain payload that `parse_payload` decodes back to its inputs.
+pub fn build_payload(variant: PreProcessedTraceVariant, felts: &[FieldElement], root: &[u8]) -> Vec<u8> {
+    encode(VERSION, variant, felts, root)
+}
+
+/// Encodes a `ROOTS_VERSION` payload naming the pre-state root as well.
+pub fn build_roots_payload(
+    variant: PreProcessedTraceVariant,
+    felts: &[FieldElement],
+    new_root: &[u8],
+    old_root: &[u8],
+) -> Vec<u8> {
+    let mut bytes = encode(ROOTS_VERSION, variant, felts, new_root);
+    bytes.extend_from_slice(&(old_root.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(old_root);
+    bytes
+}
+
+fn encode(version: u8, variant: PreProcessedTraceVariant, felts: &[FieldElement], root: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + 4 + 32 * felts.len() + 4 + root.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(version);
+    bytes.push(variant_byte(&variant));
+    bytes.extend_from_slice(&(felts.len() as u32).to_be_bytes());
+    for felt in felts {
+        bytes.extend_from_slice(&felt.to_bytes_be());
+    }
+    bytes.extend_from_slice(&(root.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(root);
+    bytes
+}
+
+/// The `(total, index)` of a chunk, or `None` if `bytes` is not one.
+pub fn chunk_header(bytes: &[u8]) -> Option<(u16, u16)> {
+    if bytes.len() < CHUNK_HEADER_LEN || &bytes[..4] != CHUNK_MAGIC {
+        return None;
+    }
+    Some((u16::from_be_bytes([bytes[4], bytes[5]]), u16::from_be_bytes([bytes[6], bytes[7]])))
+}
+
+/// Splits `payload` into chunks carrying at most `body_len` of its bytes each;
+/// chunk `i` goes in input `i`'s envelope. `None` past `u16::MAX` chunks.
+pub fn split_payload(payload: &[u8], body_len: usize) -> Option<Vec<Vec<u8>>> {
+    let bodies: Vec<&[u8]> = payload.chunks(body_len.max(1)).collect();
+    let total = u16::try_from(bodies.len()).ok()?;
+    let chunks = bodies
+        .iter()
+        .zip(0u16..)
+        .map(|(body, index)| {
+            let mut chunk = Vec::with_capacity(CHUNK_HEADER_LEN + body.len());
+            chunk.extend_from_slice(CHUNK_MAGIC);
+            chunk.extend_from_slice(&total.to_be_bytes());
+            chunk.extend_from_slice(&index.to_be_bytes());
+            chunk.extend_from_slice(body);
+            chunk
+        })
+        .collect();
+    Some(chunks)
+}
+
+/// Concatenates chunk bodies, requiring `chunks[i]` to be chunk `i` of `chunks.len()`.
+pub fn reassemble_chunks(chunks: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        match chunk_header(chunk) {
+            Some((total, i)) if usize::from(total) == chunks.len() && usize::from(i) == index => {
+                out.extend_from_slice(&chunk[CHUNK_HEADER_LEN..]);
+            }
+            _ => return Err(ErrorCode::BadChunk.into()),
+        }
+    }
+    Ok(out)
+}
+
+/// Gunzips a version-2 or -4 payload body, refusing to grow past `MAX_INFLATED_PAYLOAD`.
+fn inflate(compressed: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    GzDecoder::new(compressed)
+        .take(MAX_INFLATED_PAYLOAD as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| ErrorCode::BadCompression)?;
+    if out.len() > MAX_INFLATED_PAYLOAD {
+        return Err(ErrorCode::PayloadTooLarge.into());
+    }
+    Ok(out)
+}
+
+/// Decodes a SATC payload into its variant, proof felts and claimed new root.
+pub fn parse_payload(bytes: &[u8]) -> Result<(PreProcessedTraceVariant, Vec<FieldElement>, Vec<u8>)> {
+    let payload = decode_payload(bytes)?;
+    Ok((payload.variant, payload.felts, payload.new_root))
+}
+
+/// Decodes a SATC payload of any supported version.
+pub fn decode_payload(bytes: &[u8]) -> Result<Payload> {
+    // Expect magic
+    if bytes.len() < 4 {
+        return Err(ErrorCode::PayloadTooShort.into());
+    }
+    let magic = &bytes[0..4];
+    if magic != MAGIC {
+        return Err(ErrorCode::BadMagic.into());
+    }
+    if bytes.len() < 6 {
+        return Err(ErrorCode::PayloadTooShort.into());
+    }
+    let version = bytes[4];
+    let inflated;
+    let mut bytes: &[u8] = match version {
+        VERSION | ROOTS_VERSION => &bytes[5..],
+        COMPRESSED_VERSION | COMPRESSED_ROOTS_VERSION => {
+            inflated = inflate(&bytes[5..])?;
+            &inflated
+        }
+        _ => return Err(ErrorCode::UnsupportedVersion.into()),
+    };
+    if bytes.is_empty() {
+        return Err(ErrorCode::PayloadTooShort.into());
+    }
+    let preprocessed_variant = match bytes[0] {
+        0 => PreProcessedTraceVariant::Canonical,
+        1 => PreProcessedTraceVariant::CanonicalWithoutPedersen,
+        _ => return Err(ErrorCode::UnknownVariant.into()),
+    };
+    bytes = &bytes[1..];
+    if bytes.len() < 4 {
+        return Err(ErrorCode::PayloadTooShort.into());
+    }
+    let n = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    bytes = &bytes[4..];
+    // Bound N and check the buffer really holds it before reserving anything.
+    if n > MAX_FELTS {
+        return Err(ErrorCode::TooManyFelts.into());
+    }
+    if bytes.len() < 32 * n + 4 {
+        return Err(ErrorCode::ProofBytesTooShort.into());
+    }
+    let mut felts: Vec<FieldElement> = Vec::with_capacity(n);
+    for i in 0..n {
+        let word = &bytes[32 * i..32 * (i + 1)];
+        let arr: [u8; 32] = word.try_into().map_err(|_| ErrorCode::BadFelt)?;
+        let fe = FieldElement::from_bytes_be(&arr).map_err(|_| ErrorCode::BadFelt)?;
+        felts.push(fe);
+    }
+    bytes = &bytes[32 * n..];
+    let l = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    bytes = &bytes[4..];
+    if bytes.len() < l {
+        return Err(ErrorCode::RootBytesTooShort.into());
+    }
+    let new_root = bytes[..l].to_vec();
+    bytes = &bytes[l..];
+    let old_root = if matches!(version, ROOTS_VERSION | COMPRESSED_ROOTS_VERSION) {
+        if bytes.len() < 4 {
+            return Err(ErrorCode::RootBytesTooShort.into());
+        }
+        let l = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        bytes = &bytes[4..];
+        if bytes.len() < l {
+            return Err(ErrorCode::RootBytesTooShort.into());
+        }
+        Some(bytes[..l].to_vec())
+    } else {
+        None
+    };
+    Ok(Payload { variant: preprocessed_variant, felts, new_root, old_root })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn build_then_parse_round_trips() {
+        let cases: Vec<(PreProcessedTraceVariant, Vec<FieldElement>, Vec<u8>)> = vec![
+            (PreProcessedTraceVariant::Canonical, vec![], vec![]),
+            (PreProcessedTraceVariant::Canonical, vec![FieldElement::from(7u64)], vec![0x5a; 32]),
+            (
+                PreProcessedTraceVariant::CanonicalWithoutPedersen,
+                vec![FieldElement::ZERO, FieldElement::ONE, FieldElement::MAX],
+                vec![1, 2, 3],
+            ),
+            (
+                PreProcessedTraceVariant::CanonicalWithoutPedersen,
+                (0u64..64).map(FieldElement::from).collect(),
+                vec![0xff; 32],
+            ),
+        ];
+
+        for (variant, felts, root) in cases {
+            let bytes = build_payload(variant, &felts, &root);
+            assert_eq!(&bytes[..4], MAGIC);
+            assert_eq!(bytes[4], VERSION);
+
+            let (parsed_variant, parsed_felts, parsed_root) = parse_payload(&bytes).unwrap();
+            assert_eq!(variant_byte(&parsed_variant), variant_byte(&variant));
+            assert_eq!(parsed_felts, felts);
+            assert_eq!(parsed_root, root);
+        }
+    }
+
+    /// Plain payload declaring `n` felts but carrying only `carried` zero bytes after the count.
+    fn declaring(n: u32, carried: usize) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[VERSION, 0]);
+        bytes.extend_from_slice(&n.to_be_bytes());
+        bytes.resize(bytes.len() + carried, 0);
+        bytes
+    }
+
+    #[wasm_bindgen_test]
+    fn absurd_felt_count_is_rejected_before_allocating() {
+        let err = parse_payload(&declaring(u32::MAX, 64)).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::TooManyFelts));
+
+        let err = parse_payload(&declaring(MAX_FELTS as u32 + 1, 0)).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::TooManyFelts));
+    }
+
+    #[wasm_bindgen_test]
+    fn felt_count_within_bound_still_needs_the_bytes() {
+        let err = parse_payload(&declaring(MAX_FELTS as u32, 64)).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::ProofBytesTooShort));
+    }
+
+    #[wasm_bindgen_test]
+    fn roots_payload_carries_the_old_root() {
+        let felts = [FieldElement::from(7u64)];
+        let bytes = build_roots_payload(PreProcessedTraceVariant::Canonical, &felts, &[0x5a; 32], &[0x11; 32]);
+        assert_eq!(bytes[4], ROOTS_VERSION);
+
+        let payload = decode_payload(&bytes).unwrap();
+        assert_eq!(payload.felts, felts);
+        assert_eq!(payload.new_root, vec![0x5a; 32]);
+        assert_eq!(payload.old_root, Some(vec![0x11; 32]));
+        let plain = build_payload(PreProcessedTraceVariant::Canonical, &felts, &[0x5a; 32]);
+        assert_eq!(decode_payload(&plain).unwrap().old_root, None);
+
+        let err = decode_payload(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::RootBytesTooShort));
+    }
+
+    #[wasm_bindgen_test]
+    fn chunks_reassemble_only_in_order() {
+        let bytes = build_payload(PreProcessedTraceVariant::Canonical, &[FieldElement::from(7u64)], &[0x5a; 32]);
+        let mut chunks = split_payload(&bytes, 20).unwrap();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunk_header(&chunks[2]), Some((3, 2)));
+        assert_eq!(reassemble_chunks(&chunks).unwrap(), bytes);
+
+        chunks.swap(1, 2);
+        let err = reassemble_chunks(&chunks).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::BadChunk));
+        let err = reassemble_chunks(&chunks[..2]).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::BadChunk));
+    }
+}
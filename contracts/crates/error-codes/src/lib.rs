@@ -0,0 +1,165 @@
+//! Stable numeric error codes shared by the Sat City contracts.
+//!
+//! Alkanes surfaces a reverted call only as its error message, so every
+//! contract error renders as `[E<code>] <NAME>`, optionally followed by
+//! `: <detail>`, e.g. `[E0003] VERIFICATION_FAILED: ...`. Clients recover the
+//! code with `ErrorCode::parse` instead of matching on the name or detail.
+//!
+//! Codes are append-only: never renumber or reuse one.
+
+use std::fmt;
+
+macro_rules! error_codes {
+    ($($(#[$doc:meta])* $variant:ident = $code:literal => $name:literal,)*) => {
+        #[repr(u32)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum ErrorCode {
+            $($(#[$doc])* $variant = $code,)*
+        }
+
+        impl ErrorCode {
+            /// Every code, in numeric order.
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$variant,)*];
+
+            /// The `SCREAMING_CASE` name printed after the code.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $name,)*
+                }
+            }
+
+            pub const fn from_u32(code: u32) -> Option<Self> {
+                match code {
+                    $($code => Some(ErrorCode::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    // SATC payload parsing and proof verification
+    BadMagic = 1 => "BAD_MAGIC",
+    UnsupportedVersion = 2 => "UNSUPPORTED_VERSION",
+    VerificationFailed = 3 => "VERIFICATION_FAILED",
+    PayloadTooShort = 4 => "PAYLOAD_TOO_SHORT",
+    PayloadTooLarge = 5 => "PAYLOAD_TOO_LARGE",
+    BadCompression = 6 => "BAD_COMPRESSION",
+    UnknownVariant = 7 => "UNKNOWN_VARIANT",
+    TooManyFelts = 8 => "TOO_MANY_FELTS",
+    ProofBytesTooShort = 9 => "PROOF_BYTES_TOO_SHORT",
+    RootBytesTooShort = 10 => "ROOT_BYTES_TOO_SHORT",
+    BadFelt = 11 => "BAD_FELT",
+    BadLeaf = 12 => "BAD_LEAF",
+    // Verifier
+    NoStateRoot = 13 => "NO_STATE_ROOT",
+    NoWitnessPayload = 14 => "NO_WITNESS_PAYLOAD",
+    EmptyWitnessPayload = 15 => "EMPTY_WITNESS_PAYLOAD",
+    TxidDecodeFailed = 16 => "TXID_DECODE_FAILED",
+    Downgrade = 17 => "DOWNGRADE",
+    AlreadyAtVersion = 18 => "ALREADY_AT_VERSION",
+    // Shared
+    AlreadyInitialized = 19 => "ALREADY_INITIALIZED",
+    Unauthorized = 20 => "UNAUTHORIZED",
+    Paused = 21 => "PAUSED",
+    // GameEscrow
+    EmptyDeposit = 22 => "EMPTY_DEPOSIT",
+    InvalidDeposit = 23 => "INVALID_DEPOSIT",
+    DepositOverflow = 24 => "DEPOSIT_OVERFLOW",
+    ImplNotSet = 25 => "IMPL_NOT_SET",
+    ImplIsSelf = 26 => "IMPL_IS_SELF",
+    BadPositionToken = 27 => "BAD_POSITION_TOKEN",
+    InvalidPositionToken = 28 => "INVALID_POSITION_TOKEN",
+    UnknownPosition = 29 => "UNKNOWN_POSITION",
+    InvalidAmount = 30 => "INVALID_AMOUNT",
+    InsufficientPositionBalance = 31 => "INSUFFICIENT_POSITION_BALANCE",
+    InsufficientFtBalance = 32 => "INSUFFICIENT_FT_BALANCE",
+    // Chunked witness payloads
+    MissingChunk = 33 => "MISSING_CHUNK",
+    BadChunk = 34 => "BAD_CHUNK",
+    // Inclusion witnesses
+    BadLeafIndex = 35 => "BAD_LEAF_INDEX",
+    // Version-3 payloads
+    StaleRoot = 36 => "STALE_ROOT",
+    MissingOldRoot = 37 => "MISSING_OLD_ROOT",
+    // GameEscrow
+    ImplNotTemplate = 38 => "IMPL_NOT_TEMPLATE",
+}
+
+impl ErrorCode {
+    pub const fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// Attaches free-form context, rendered after the code and name.
+    pub fn with(self, detail: impl fmt::Display) -> ContractError {
+        ContractError { code: self, detail: detail.to_string() }
+    }
+
+    /// The code carried by `err`, if it was raised as an `ErrorCode` or `ContractError`.
+    pub fn of(err: &anyhow::Error) -> Option<Self> {
+        err.downcast_ref::<ErrorCode>()
+            .copied()
+            .or_else(|| err.downcast_ref::<ContractError>().map(|e| e.code))
+    }
+
+    /// Recovers the code from a revert message or `VerifyOnly` response, which
+    /// may carry a runtime prefix before the `[E<code>]` marker.
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        let start = message.windows(2).position(|w| w == b"[E")? + 2;
+        let rest = &message[start..];
+        let end = rest.iter().position(|&b| b == b']')?;
+        let code = std::str::from_utf8(&rest[..end]).ok()?.parse().ok()?;
+        Self::from_u32(code)
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[E{:04}] {}", self.code(), self.name())
+    }
+}
+
+impl std::error::Error for ErrorCode {}
+
+/// An `ErrorCode` with context, e.g. the underlying verifier error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractError {
+    pub code: ErrorCode,
+    pub detail: String,
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.detail)
+    }
+}
+
+impl std::error::Error for ContractError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn codes_round_trip_through_u32() {
+        for (i, &code) in ErrorCode::ALL.iter().enumerate() {
+            assert_eq!(code.code(), i as u32 + 1);
+            assert_eq!(ErrorCode::from_u32(code.code()), Some(code));
+        }
+        assert_eq!(ErrorCode::from_u32(0), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn codes_parse_back_out_of_revert_messages() {
+        let err: anyhow::Error = ErrorCode::VerificationFailed.with("bad oods").into();
+        let message = format!("ALKANES: revert: {err}");
+
+        assert_eq!(err.to_string(), "[E0003] VERIFICATION_FAILED: bad oods");
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::VerificationFailed));
+        assert_eq!(ErrorCode::parse(message.as_bytes()), Some(ErrorCode::VerificationFailed));
+        assert_eq!(ErrorCode::parse(b"Contract already initialized"), None);
+    }
+}
@@ -0,0 +1,262 @@
+//! Command-line block building: load the pre-state dumped at `--state`, fetch
+//! the SatCity transactions indexed from `--from-height` onwards, prove them as
+//! one block and write the SATC witness payload to `--output`.
+//!
+//! Exit codes:
+//! - 0: payload written
+//! - 2: bad arguments or `SATCITY_*` RPC environment (see `RpcConfig::from_env`)
+//! - 3: RPC failure while fetching transactions
+//! - 4: the block failed to prove
+//! - 5: the payload could not be written
+//! - 6: the `--state` dump could not be read or parsed
+
+use crate::mempool::Mempool;
+use crate::prover::{ProvedBlock, Prover, ProverError};
+use crate::rpc::{ConfigError, RpcClient, RpcClientError, RpcConfig};
+use crate::state::State;
+use serde_json::json;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_RPC: i32 = 3;
+pub const EXIT_PROVE: i32 = 4;
+pub const EXIT_IO: i32 = 5;
+pub const EXIT_STATE: i32 = 6;
+
+pub const USAGE: &str = "usage: sequencer --state <PATH> --from-height <HEIGHT> --output <PATH> [--max-txs <N>]";
+
+const DEFAULT_MAX_TXS: usize = 256;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    pub from_height: u64,
+    pub max_txs:     usize,
+    pub output:      PathBuf,
+    pub state:       PathBuf,
+}
+
+impl Args {
+    /// Parses the arguments after the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut from_height = None;
+        let mut max_txs = DEFAULT_MAX_TXS;
+        let mut output = None;
+        let mut state = None;
+
+        let mut it = args.into_iter();
+        while let Some(flag) = it.next() {
+            match flag.as_str() {
+                "--from-height" => {
+                    from_height = Some(value(&mut it, &flag)?.parse().map_err(|_| "--from-height must be a block height")?)
+                }
+                "--max-txs" => {
+                    max_txs = value(&mut it, &flag)?.parse().map_err(|_| "--max-txs must be a count")?
+                }
+                "--output" => output = Some(PathBuf::from(value(&mut it, &flag)?)),
+                "--state" => state = Some(PathBuf::from(value(&mut it, &flag)?)),
+                other => return Err(format!("unknown argument {other}")),
+            }
+        }
+
+        Ok(Self {
+            from_height: from_height.ok_or("--from-height is required")?,
+            max_txs,
+            output:      output.ok_or("--output is required")?,
+            state:       state.ok_or("--state is required")?,
+        })
+    }
+}
+
+fn value<I: Iterator<Item = String>>(it: &mut I, flag: &str) -> Result<String, String> {
+    it.next().ok_or_else(|| format!("{flag} needs a value"))
+}
+
+#[derive(Debug)]
+pub enum BlockError {
+    Config(ConfigError),
+    Rpc(RpcClientError),
+    Prove(ProverError),
+    Io(io::Error),
+    State(String),
+}
+
+impl BlockError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BlockError::Config(_) => EXIT_USAGE,
+            BlockError::Rpc(_) => EXIT_RPC,
+            BlockError::Prove(_) => EXIT_PROVE,
+            BlockError::Io(_) => EXIT_IO,
+            BlockError::State(_) => EXIT_STATE,
+        }
+    }
+}
+
+impl From<ConfigError> for BlockError {
+    fn from(err: ConfigError) -> Self { BlockError::Config(err) }
+}
+
+impl From<RpcClientError> for BlockError {
+    fn from(err: RpcClientError) -> Self { BlockError::Rpc(err) }
+}
+
+impl From<ProverError> for BlockError {
+    fn from(err: ProverError) -> Self { BlockError::Prove(err) }
+}
+
+impl From<io::Error> for BlockError {
+    fn from(err: io::Error) -> Self { BlockError::Io(err) }
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::Config(err) => write!(f, "config: {err}"),
+            BlockError::Rpc(err) => write!(f, "rpc: {err}"),
+            BlockError::Prove(err) => write!(f, "prove: {err}"),
+            BlockError::Io(err) => write!(f, "write: {err}"),
+            BlockError::State(err) => write!(f, "state: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// Fetches, proves and writes one block as described by `args`.
+pub async fn run(args: Args) -> Result<ProvedBlock, BlockError> {
+    let state = load_state(&args.state)?;
+    let rpc = RpcClient::new(RpcConfig::from_env()?);
+    let mut mempool = Mempool::new();
+    fetch_into(&rpc, &mut mempool, args.from_height, args.max_txs).await?;
+
+    build_block(&Prover::new(), &state, &mut mempool, args.max_txs, &args.output)
+}
+
+/// Reads a `State::to_json` dump and commits it, so its root is the block's old root.
+pub fn load_state(path: &Path) -> Result<State, BlockError> {
+    let json = std::fs::read_to_string(path).map_err(|err| BlockError::State(format!("{}: {err}", path.display())))?;
+    let mut state = State::from_json(&json).map_err(|err| BlockError::State(format!("{}: {err}", path.display())))?;
+    state.commit();
+    Ok(state)
+}
+
+/// Pools indexed transactions from `from_height` up to the chain tip, stopping
+/// once `max_txs` are queued.
+async fn fetch_into(rpc: &RpcClient, mempool: &mut Mempool, from_height: u64, max_txs: usize) -> Result<(), BlockError> {
+    let tip = rpc
+        .bitcoin_call("getblockcount", json!([]))
+        .await?
+        .as_u64()
+        .ok_or_else(|| RpcClientError::Decode("getblockcount did not return a height".to_string()))?;
+
+    let mut pooled = 0;
+    for height in from_height..=tip {
+        for tx in rpc.fetch_satcity_txs(height).await? {
+            if pooled >= max_txs {
+                return Ok(());
+            }
+            // The indexer is trusted; the prover still rejects bad transfers.
+            if mempool.add_transaction(tx) {
+                pooled += 1;
+            }
+        }
+        if pooled >= max_txs {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Drains up to `max_txs` from `mempool`, proves them against `state` and
+/// writes the block's witness payload to `output`.
+pub fn build_block(
+    prover: &Prover,
+    state: &State,
+    mempool: &mut Mempool,
+    max_txs: usize,
+    output: &Path,
+) -> Result<ProvedBlock, BlockError> {
+    let txs = mempool.get_transactions(max_txs);
+    let block = prover.prove_block(&txs, state)?;
+    std::fs::write(output, block.witness_payload())?;
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::Transaction;
+    use crate::state::{AlkaneId, Player};
+    use ethnum::U256;
+
+    fn args(list: &[&str]) -> Result<Args, String> {
+        Args::parse(list.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parses_all_flags() {
+        assert_eq!(
+            args(&["--from-height", "840000", "--max-txs", "8", "--output", "block.bin", "--state", "state.json"]),
+            Ok(Args {
+                from_height: 840000,
+                max_txs:     8,
+                output:      PathBuf::from("block.bin"),
+                state:       PathBuf::from("state.json"),
+            })
+        );
+    }
+
+    #[test]
+    fn max_txs_defaults() {
+        assert_eq!(args(&["--output", "b", "--from-height", "1", "--state", "s"]).unwrap().max_txs, DEFAULT_MAX_TXS);
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_flags() {
+        assert!(args(&["--output", "b", "--state", "s"]).is_err());
+        assert!(args(&["--from-height", "1", "--output", "b"]).is_err());
+        assert!(args(&["--from-height"]).is_err());
+        assert!(args(&["--from-height", "1", "--output", "b", "--state", "s", "--fast"]).is_err());
+    }
+
+    #[test]
+    fn build_block_writes_payload() {
+        let mut state = State::new();
+        let p1 = AlkaneId { block: 1, tx: 1 };
+        let p2 = AlkaneId { block: 1, tx: 2 };
+        state.upsert_player(Player { id: p1, chips_balance: U256::from(100u128) });
+        state.upsert_player(Player { id: p2, chips_balance: U256::from(50u128) });
+        state.commit();
+
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(Transaction::TransferChips { from: p1, to: p2, amount: 10u128.into(), nonce: 0 });
+
+        let output = std::env::temp_dir().join(format!("satcity-block-{}.bin", std::process::id()));
+        let block = build_block(&Prover::new(), &state, &mut mempool, 16, &output).expect("block must prove");
+        let written = std::fs::read(&output).expect("payload must be written");
+        std::fs::remove_file(&output).ok();
+
+        assert_eq!(&written[..4], b"SATC");
+        assert_eq!(written[4], 3);
+        assert!(written.ends_with(&block.old_root));
+        assert_eq!(written, block.witness_payload());
+        assert!(mempool.get_transactions(usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn load_state_commits_the_dump() {
+        let mut state = State::new();
+        state.upsert_player(Player { id: AlkaneId { block: 1, tx: 1 }, chips_balance: U256::from(100u128) });
+        state.commit();
+
+        let path = std::env::temp_dir().join(format!("satcity-state-{}.json", std::process::id()));
+        std::fs::write(&path, state.to_json()).unwrap();
+        let loaded = load_state(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.expect("dump must load").root(), state.root());
+        assert!(matches!(load_state(&path), Err(BlockError::State(_))));
+    }
+}
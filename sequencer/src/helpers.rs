@@ -3,12 +3,14 @@ use ethnum::U256;
 use crate::state::{Player, OrbitalNft, AlkaneId};
 use crate::mempool::{Transaction};
 use cairo_vm::types::relocatable::MaybeRelocatable;
+use std::fmt;
 
 /// ---- Player & NFT flattening ----------------------------------------
 
+/// Cairo's `u256` serializes as (low, high); ethnum's `into_words` is (high, low).
 fn split_u256(x: U256) -> (Felt252, Felt252) {
-   let (lo, hi) = x.into_words();           
-   (Felt252::from(lo), Felt252::from(hi)) 
+   let (hi, lo) = x.into_words();
+   (Felt252::from(lo), Felt252::from(hi))
 }
 
 pub fn encode_players(players: &[Player]) -> Vec<Felt252> {
@@ -30,22 +32,36 @@ pub fn encode_nfts(nfts: &[OrbitalNft]) -> Vec<Felt252> {
     nfts.iter()
         .flat_map(|n| {
             let (id_lo, id_hi) = split_u256(n.id.into());
+            let (data_lo, data_hi) = split_u256(U256::from_le_bytes(n.data));
             vec![
                 id_lo,
                 id_hi,
                 Felt252::from(n.owner.block),
                 Felt252::from(n.owner.tx),
+                data_lo,
+                data_hi,
             ]
         })
         .collect()
 }
 
 /// ---- Transaction flattening -----------------------------------------
+///
+/// Each transaction is its Cairo `Serde` form: the variant tag, then fields.
+///
+/// | tag | variant         | fields                                   |
+/// |-----|-----------------|------------------------------------------|
+/// | 0   | `TransferChips` | from, to, amount (lo, hi), nonce         |
+/// | 1   | `TransferNft`   | from, to, nft_id (lo, hi), nonce         |
+/// | 2   | `MintChips`     | to, amount (lo, hi)                      |
+/// | 3   | `BurnChips`     | from, amount (lo, hi), nonce             |
+///
+/// The tag order must match the `Transaction` enum in `circuits/src/lib.cairo`.
 
 pub fn encode_txs(txs: &[Transaction]) -> Vec<Felt252> {
     txs.iter()
         .flat_map(|t| match t {
-            Transaction::TransferChips { from, to, amount } => {
+            Transaction::TransferChips { from, to, amount, nonce } => {
                 let (a_lo, a_hi) = split_u256((*amount).into());
                 vec![
                     Felt252::from(0u8),                              // tag
@@ -55,9 +71,10 @@ pub fn encode_txs(txs: &[Transaction]) -> Vec<Felt252> {
                     Felt252::from(to.tx),
                     a_lo,
                     a_hi,
+                    Felt252::from(*nonce),
                 ]
             }
-            Transaction::TransferNft { from, to, nft_id } => {
+            Transaction::TransferNft { from, to, nft_id, nonce } => {
                 let (id_lo, id_hi) = split_u256((*nft_id).into());
                 vec![
                     Felt252::from(1u8),                              // tag
@@ -67,74 +84,235 @@ pub fn encode_txs(txs: &[Transaction]) -> Vec<Felt252> {
                     Felt252::from(to.tx),
                     id_lo,
                     id_hi,
+                    Felt252::from(*nonce),
+                ]
+            }
+            Transaction::MintChips { to, amount } => {
+                let (a_lo, a_hi) = split_u256((*amount).into());
+                vec![
+                    Felt252::from(2u8),                              // tag
+                    Felt252::from(to.block),
+                    Felt252::from(to.tx),
+                    a_lo,
+                    a_hi,
+                ]
+            }
+            Transaction::BurnChips { from, amount, nonce } => {
+                let (a_lo, a_hi) = split_u256((*amount).into());
+                vec![
+                    Felt252::from(3u8),                              // tag
+                    Felt252::from(from.block),
+                    Felt252::from(from.tx),
+                    a_lo,
+                    a_hi,
+                    Felt252::from(*nonce),
                 ]
             }
         })
         .collect()
 }
 
-pub fn as_felt(value: &MaybeRelocatable) -> Felt252 {
+/// ---- Decoding Cairo return values -----------------------------------
+
+/// Why the circuit's return values could not be decoded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The values ended before a complete record was read.
+    Truncated,
+    /// A felt does not fit the integer type it decodes into.
+    FeltTooLarge,
+    /// A relocatable pointer appeared where an integer felt was expected.
+    ExpectedInt,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "return values truncated"),
+            DecodeError::FeltTooLarge => write!(f, "felt out of range"),
+            DecodeError::ExpectedInt => write!(f, "expected an integer felt, got relocatable"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub fn as_felt(value: &MaybeRelocatable) -> Result<Felt252, DecodeError> {
     match value {
-        MaybeRelocatable::Int(f) => *f,
-        _ => panic!("expected an integer felt, got relocatable"),
+        MaybeRelocatable::Int(f) => Ok(*f),
+        _ => Err(DecodeError::ExpectedInt),
     }
 }
 
-fn next_felt<'a, I>(it: &mut I) -> Felt252
+fn next_felt<'a, I>(it: &mut I) -> Result<Felt252, DecodeError>
 where
     I: Iterator<Item = &'a MaybeRelocatable>,
 {
-    as_felt(it.next().expect("not enough data"))
+    as_felt(it.next().ok_or(DecodeError::Truncated)?)
 }
 
+fn next_u128<'a, I>(it: &mut I) -> Result<u128, DecodeError>
+where
+    I: Iterator<Item = &'a MaybeRelocatable>,
+{
+    next_felt(it)?.to_biguint().try_into().map_err(|_| DecodeError::FeltTooLarge)
+}
+
+fn next_len<'a, I>(it: &mut I) -> Result<usize, DecodeError>
+where
+    I: Iterator<Item = &'a MaybeRelocatable>,
+{
+    next_felt(it)?.to_biguint().try_into().map_err(|_| DecodeError::FeltTooLarge)
+}
 
-pub fn decode_players<'a, I>(it: &mut I) -> Vec<Player>
+/// Reads a Cairo `u256` (low felt, then high felt).
+fn next_u256<'a, I>(it: &mut I) -> Result<U256, DecodeError>
 where
     I: Iterator<Item = &'a MaybeRelocatable>,
 {
-    let len: usize = next_felt(it).to_biguint().try_into().unwrap();
+    let lo = next_u128(it)?;
+    let hi = next_u128(it)?;
+    Ok(U256::from_words(hi, lo))
+}
 
+pub fn decode_players<'a, I>(it: &mut I) -> Result<Vec<Player>, DecodeError>
+where
+    I: Iterator<Item = &'a MaybeRelocatable>,
+{
+    let len = next_len(it)?;
+
+    // No preallocation: `len` comes from the prover and is not trusted.
     (0..len)
         .map(|_| {
-            Player {
+            Ok(Player {
                 id: AlkaneId {
-                    block: next_felt(it).to_biguint().try_into().unwrap(),
-                    tx:    next_felt(it).to_biguint().try_into().unwrap(),
+                    block: next_u128(it)?,
+                    tx:    next_u128(it)?,
                 },
-                chips_balance: {
-                    let lo = next_felt(it).to_biguint();
-                    let hi = next_felt(it).to_biguint();
-                    U256::from_words(hi.try_into().unwrap(), lo.try_into().unwrap())
-                },
-            }
+                chips_balance: next_u256(it)?,
+            })
         })
         .collect()
 }
 
-pub fn decode_nfts<'a, I>(it: &mut I) -> Vec<OrbitalNft>
+pub fn decode_nfts<'a, I>(it: &mut I) -> Result<Vec<OrbitalNft>, DecodeError>
 where
     I: Iterator<Item = &'a MaybeRelocatable>,
 {
     // first felt is the array length
-    let len: usize = next_felt(it).to_biguint().try_into().unwrap();
-    let mut nfts = Vec::with_capacity(len);
+    let len = next_len(it)?;
+    let mut nfts = Vec::new();
 
     for _ in 0..len {
         // u256 -> two felts (little-endian: low first, then high)
-        let lo = next_felt(it).to_biguint();
-        let hi = next_felt(it).to_biguint();
-        let id = U256::from_words(
-            hi.try_into().unwrap(),   // high 128 bits
-            lo.try_into().unwrap(),   // low  128 bits
-        );
+        let id = next_u256(it)?;
 
         // AlkaneId -> two u128 felts
-        let owner_block: u128 = next_felt(it).to_biguint().try_into().unwrap();
-        let owner_tx:    u128 = next_felt(it).to_biguint().try_into().unwrap();
-        let owner = AlkaneId { block: owner_block, tx: owner_tx };
+        let owner = AlkaneId { block: next_u128(it)?, tx: next_u128(it)? };
+
+        // 32 data bytes as a u256, little-endian like `encode_nfts`
+        let data = next_u256(it)?.to_le_bytes();
+
+        nfts.push(OrbitalNft { id, owner, data });
+    }
+
+    Ok(nfts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cairo_vm::types::relocatable::Relocatable;
+
+    fn ints(values: &[u128]) -> Vec<MaybeRelocatable> {
+        values.iter().map(|v| MaybeRelocatable::Int(Felt252::from(*v))).collect()
+    }
+
+    #[test]
+    fn truncated_players_is_an_error() {
+        // one player announced, but only its id follows
+        let data = ints(&[1, 1, 1]);
+        assert_eq!(decode_players(&mut data.iter()).unwrap_err(), DecodeError::Truncated);
+    }
+
+    #[test]
+    fn truncated_nfts_is_an_error() {
+        let data = ints(&[2, 42, 0, 1, 1]);
+        assert_eq!(decode_nfts(&mut data.iter()).unwrap_err(), DecodeError::Truncated);
+    }
 
-        nfts.push(OrbitalNft { id, owner });
+    #[test]
+    fn oversized_felt_is_an_error() {
+        let mut data = ints(&[1]);
+        data.push(MaybeRelocatable::Int(Felt252::from(u128::MAX) + Felt252::ONE));  // block > u128
+        data.extend(ints(&[1, 100, 0]));
+        assert_eq!(decode_players(&mut data.iter()).unwrap_err(), DecodeError::FeltTooLarge);
     }
 
-    nfts
-}
\ No newline at end of file
+    #[test]
+    fn relocatable_is_an_error() {
+        let data = vec![MaybeRelocatable::RelocatableValue(Relocatable::from((1, 0)))];
+        assert_eq!(decode_nfts(&mut data.iter()).unwrap_err(), DecodeError::ExpectedInt);
+    }
+
+    /// Values whose high and low words differ, so a swapped split shows up.
+    fn wide_values() -> Vec<U256> {
+        vec![
+            U256::ZERO,
+            U256::from(7u128),
+            (U256::ONE << 130) | U256::from(7u128),
+            U256::from_words(u128::MAX, 1),
+            U256::MAX,
+        ]
+    }
+
+    fn with_len(len: usize, felts: Vec<Felt252>) -> Vec<MaybeRelocatable> {
+        std::iter::once(Felt252::from(len as u128))
+            .chain(felts)
+            .map(MaybeRelocatable::Int)
+            .collect()
+    }
+
+    #[test]
+    fn split_u256_is_low_then_high() {
+        let x = U256::from_words(3, 5);
+        assert_eq!(split_u256(x), (Felt252::from(5u128), Felt252::from(3u128)));
+    }
+
+    #[test]
+    fn nfts_round_trip() {
+        let nfts: Vec<OrbitalNft> = wide_values()
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| OrbitalNft { id, owner: AlkaneId { block: 2, tx: i as u128 }, data: (!id).to_le_bytes() })
+            .collect();
+
+        let data = with_len(nfts.len(), encode_nfts(&nfts));
+        let decoded = decode_nfts(&mut data.iter()).unwrap();
+
+        assert_eq!(decoded.len(), nfts.len());
+        for (got, want) in decoded.iter().zip(&nfts) {
+            assert_eq!(got.id, want.id);
+            assert_eq!(got.owner, want.owner);
+            assert_eq!(got.data, want.data);
+        }
+    }
+
+    #[test]
+    fn player_balances_round_trip() {
+        let players: Vec<Player> = wide_values()
+            .into_iter()
+            .enumerate()
+            .map(|(i, chips_balance)| Player { id: AlkaneId { block: 1, tx: i as u128 }, chips_balance })
+            .collect();
+
+        let data = with_len(players.len(), encode_players(&players));
+        let decoded = decode_players(&mut data.iter()).unwrap();
+
+        assert_eq!(decoded.len(), players.len());
+        for (got, want) in decoded.iter().zip(&players) {
+            assert_eq!(got.id, want.id);
+            assert_eq!(got.chips_balance, want.chips_balance);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+pub mod rpc;
+pub mod state;
+pub mod mempool;
+pub mod prover;
+pub mod helpers;
+pub mod cli;
+pub mod producer;
+
+#[cfg(test)]
+mod tests {
+    use crate::mempool::{Mempool, Transaction};
+    use crate::prover::{Prover, ProverConfig, ProverError};
+    use crate::state::{AlkaneId, Player, OrbitalNft, State, StateError, EMPTY_ROOT};
+    use ethnum::U256;
+
+    fn id(block: u128, tx: u128) -> AlkaneId {
+        AlkaneId { block, tx }
+    }
+
+    #[test]
+    fn chips_and_nft_flow() {
+        /* ---------- arrange ---------- */
+        let mut state = State::new();
+
+        // two players
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        let p2 = Player { id: id(1, 2), chips_balance: U256::from(50u128) };
+        state.upsert_player(p1.clone());
+        state.upsert_player(p2.clone());
+
+        // one NFT owned by p1
+        let nft = OrbitalNft { id: U256::from(42u128), owner: p1.id, data: [0; 32] };
+        state.upsert_nft(nft.clone());
+
+        // seal the pre-state Merkle root
+        state.commit();
+        let old_root = state.root().expect("root must exist");
+
+        /* ---------- build block ---------- */
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(Transaction::TransferChips {
+            from: p1.id,
+            to:   p2.id,
+            amount: 10u128.into(),
+            nonce: 0,
+        });
+        mempool.add_transaction(Transaction::TransferNft {
+            from: p1.id,
+            to:   p2.id,
+            nft_id: nft.id,
+            nonce: 1,
+        });
+
+        /* ---------- prove ---------- */
+        let prover = Prover::new();
+        let txs    = mempool.get_transactions(usize::MAX);
+        let (_, new_root, _) = prover.prove(&txs, &state)
+                    .expect("Cairo program failed");
+
+        /* ---------- assert ---------- */
+        assert_ne!(old_root, new_root);        // roots must differ :contentReference[oaicite:6]{index=6}
+    }
+
+    #[test]
+    fn apply_block_matches_proven_root() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        let p2 = Player { id: id(1, 2), chips_balance: U256::from(50u128) };
+        state.upsert_player(p1.clone());
+        state.upsert_player(p2.clone());
+        state.upsert_nft(OrbitalNft { id: U256::from(42u128), owner: p1.id, data: [0; 32] });
+        state.commit();
+
+        let txs = vec![
+            Transaction::TransferChips { from: p1.id, to: p2.id, amount: 10u128.into(), nonce: 0 },
+            Transaction::TransferNft { from: p1.id, to: p2.id, nft_id: U256::from(42u128), nonce: 1 },
+            Transaction::MintChips { to: id(1, 3), amount: 5u128.into() },
+            Transaction::BurnChips { from: p2.id, amount: 20u128.into(), nonce: 0 },
+        ];
+        let block = Prover::new().prove_block(&txs, &state).expect("Cairo program failed");
+
+        let mut local = state.clone();
+        let local_root = local.apply_block(&txs).expect("block must apply");
+
+        assert_eq!(local_root, block.new_root);
+        assert_eq!(local.next_nonce(&p1.id), block.post_state.next_nonce(&p1.id));
+    }
+
+    #[test]
+    fn root_only_matches_full_prove() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        let p2 = Player { id: id(1, 2), chips_balance: U256::from(50u128) };
+        state.upsert_player(p1.clone());
+        state.upsert_player(p2.clone());
+        state.upsert_nft(OrbitalNft { id: U256::from(42u128), owner: p1.id, data: [0; 32] });
+        state.commit();
+        let pre_root = state.root();
+
+        let txs = vec![
+            Transaction::TransferChips { from: p1.id, to: p2.id, amount: 10u128.into(), nonce: 0 },
+            Transaction::TransferNft { from: p1.id, to: p2.id, nft_id: U256::from(42u128), nonce: 1 },
+            Transaction::MintChips { to: id(1, 3), amount: 5u128.into() },
+        ];
+        let prover = Prover::new();
+        let root_only = prover.compute_root_only(&txs, &state).expect("block must apply");
+        let (_, proven, _) = prover.prove(&txs, &state).expect("Cairo program failed");
+
+        assert_eq!(root_only, proven);
+        assert_eq!(state.root(), pre_root);
+    }
+
+    #[test]
+    fn filtered_prove_drops_invalid_transactions() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        let p2 = Player { id: id(1, 2), chips_balance: U256::from(50u128) };
+        state.upsert_player(p1.clone());
+        state.upsert_player(p2.clone());
+        state.commit();
+
+        let valid_transfer = Transaction::TransferChips { from: p1.id, to: p2.id, amount: 10u128.into(), nonce: 0 };
+        let overdraft = Transaction::TransferChips { from: p2.id, to: p1.id, amount: 500u128.into(), nonce: 0 };
+        // nonce 1 for p2 is a gap once the overdraft at nonce 0 is dropped
+        let orphaned = Transaction::TransferChips { from: p2.id, to: p1.id, amount: 5u128.into(), nonce: 1 };
+        let mint = Transaction::MintChips { to: id(1, 3), amount: 5u128.into() };
+        let txs = vec![valid_transfer.clone(), overdraft, orphaned, mint.clone()];
+
+        let (included, new_root, proof) = Prover::new().prove_filtered(&txs, &state)
+                    .expect("accepted subset must prove");
+
+        assert_eq!(included, vec![valid_transfer, mint]);
+        let mut local = state.clone();
+        assert_eq!(local.apply_block(&included).expect("subset must apply"), new_root);
+        assert_eq!(local.player(&p1.id).unwrap().chips_balance, U256::from(90u128));
+        assert!(!proof.is_empty());
+    }
+
+    #[test]
+    fn replayed_nonce_rejected_before_proving() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        let p2 = Player { id: id(1, 2), chips_balance: U256::from(50u128) };
+        state.upsert_player(p1.clone());
+        state.upsert_player(p2.clone());
+        state.commit();
+        state.check_and_bump_nonce(p1.id, 0).unwrap();      // nonce 0 landed in an earlier block
+
+        let replay = Transaction::TransferChips { from: p1.id, to: p2.id, amount: 10u128.into(), nonce: 0 };
+        let err = Prover::new().prove(&[replay], &state).unwrap_err();
+
+        assert!(matches!(err, ProverError::State(StateError::StaleNonce { expected: 1, got: 0, .. })));
+    }
+
+    #[test]
+    fn mint_chips_changes_root() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        state.upsert_player(p1.clone());
+        state.commit();
+        let old_root = state.root().expect("root must exist");
+
+        let mint = Transaction::MintChips { to: p1.id, amount: 25u128.into() };
+        let (_, new_root, _) = Prover::new().prove(&[mint], &state)
+                    .expect("Cairo program failed");
+
+        assert_ne!(old_root, new_root);
+    }
+
+    #[test]
+    fn prove_returns_committed_pre_state_root() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        state.upsert_player(p1.clone());
+        state.commit();
+        let committed = state.root().expect("root must exist");
+
+        let mint = Transaction::MintChips { to: p1.id, amount: 25u128.into() };
+        let (old_root, new_root, proof) = Prover::new().prove(&[mint], &state)
+                    .expect("Cairo program failed");
+
+        assert_eq!(old_root, committed);
+        assert_ne!(new_root, committed);
+        assert!(!proof.is_empty());
+        assert_eq!(state.root(), Some(committed));
+    }
+
+    #[test]
+    fn uncommitted_pre_state_is_refused() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        state.upsert_player(p1.clone());
+
+        let mint = Transaction::MintChips { to: p1.id, amount: 25u128.into() };
+        let err = Prover::new().prove(&[mint], &state).unwrap_err();
+        assert!(matches!(err, ProverError::Uncommitted));
+    }
+
+    #[test]
+    fn genesis_block_proves_from_the_empty_root() {
+        let genesis = State::new();
+        let mint = Transaction::MintChips { to: id(1, 1), amount: 25u128.into() };
+
+        let block = Prover::new().prove_block(&[mint.clone()], &genesis).expect("genesis block must prove");
+        assert_eq!(block.old_root, EMPTY_ROOT);
+        assert_eq!(genesis.clone().apply_block(&[mint]), Ok(block.new_root));
+        assert_eq!(genesis.clone().apply_block(&[]), Ok(EMPTY_ROOT));
+    }
+
+    #[test]
+    fn self_transfer_neither_applies_nor_proves() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        state.upsert_player(p1.clone());
+        state.commit();
+        let committed = state.root();
+
+        let txs = vec![Transaction::TransferChips { from: p1.id, to: p1.id, amount: 40u128.into(), nonce: 0 }];
+        let mut local = state.clone();
+        assert_eq!(local.apply_block(&txs), Err(StateError::SelfTransfer(p1.id)));
+        assert_eq!(local.root(), committed);
+        assert!(matches!(Prover::new().prove_block(&txs, &state), Err(ProverError::Cairo(_))));
+    }
+
+    #[test]
+    fn burn_chips_reduces_sender_balance() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        state.upsert_player(p1.clone());
+        state.commit();
+
+        let burn = Transaction::BurnChips { from: p1.id, amount: 30u128.into(), nonce: 0 };
+        let block = Prover::new().prove_block(&[burn], &state)
+                    .expect("Cairo program failed");
+
+        let after = block.post_state.player(&p1.id).expect("burner must remain");
+        assert_eq!(after.chips_balance, U256::from(70u128));
+        assert_eq!(block.post_state.next_nonce(&p1.id), 1);
+        assert_eq!(block.post_state.root(), Some(block.new_root));
+    }
+
+    #[test]
+    fn proof_passes_self_verification() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        let p2 = Player { id: id(1, 2), chips_balance: U256::from(50u128) };
+        state.upsert_player(p1.clone());
+        state.upsert_player(p2.clone());
+        state.commit();
+
+        let prover = Prover::with_config(ProverConfig { verify_after_prove: true });
+        let transfer = Transaction::TransferChips { from: p1.id, to: p2.id, amount: 10u128.into(), nonce: 0 };
+
+        let block = prover.prove_block(&[transfer], &state);
+        assert!(block.is_ok(), "self-verification failed: {:?}", block.err().map(|e| e.to_string()));
+    }
+
+    #[test]
+    fn metrics_are_populated_for_small_block() {
+        let mut state = State::new();
+        let p1 = Player { id: id(1, 1), chips_balance: U256::from(100u128) };
+        let p2 = Player { id: id(1, 2), chips_balance: U256::from(50u128) };
+        state.upsert_player(p1.clone());
+        state.upsert_player(p2.clone());
+        state.commit();
+
+        let transfer = Transaction::TransferChips { from: p1.id, to: p2.id, amount: 10u128.into(), nonce: 0 };
+        let (block, metrics) = Prover::new().prove_with_metrics(&[transfer], &state)
+                    .expect("Cairo program failed");
+
+        assert!(metrics.cairo_run_ms > 0.0);
+        assert!(metrics.adapt_ms > 0.0);
+        assert!(metrics.prove_ms > 0.0);
+        // a stwo Cairo proof is thousands of felts even for a tiny trace
+        assert!(metrics.proof_felt_count > 1000);
+        assert_eq!(metrics.proof_felt_count, block.proof.len());
+        assert_eq!(metrics.payload_bytes, 4 + 2 + 4 + 32 * metrics.proof_felt_count + 4 + 32 + 4 + 32);
+    }
+}
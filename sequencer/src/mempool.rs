@@ -1,32 +1,650 @@
-use std::collections::VecDeque;
-use crate::state::{AlkaneId};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::state::{AlkaneId, State};
+use bitcoin::secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey};
 use ethnum::U256;
+use rs_merkle::{algorithms::Sha256, Hasher};
 
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Transaction {
-    #[allow(dead_code)]
-    TransferChips { from: AlkaneId, to: AlkaneId, amount: U256 },
-    #[allow(dead_code)]
-    TransferNft { from: AlkaneId, to: AlkaneId, nft_id: U256 },
+    TransferChips { from: AlkaneId, to: AlkaneId, amount: U256, nonce: u64 },
+    TransferNft { from: AlkaneId, to: AlkaneId, nft_id: U256, nonce: u64 },
+    /// Credits chips bridged in from an L1 escrow deposit. Not signed by a
+    /// player, so it carries no nonce.
+    MintChips { to: AlkaneId, amount: U256 },
+    /// Destroys chips so the bridge can release the matching L1 assets.
+    BurnChips { from: AlkaneId, amount: U256, nonce: u64 },
+}
+
+impl Transaction {
+    /// Player who authored the transaction; `None` for bridge mints.
+    pub fn sender(&self) -> Option<AlkaneId> {
+        match self {
+            Transaction::TransferChips { from, .. }
+            | Transaction::TransferNft { from, .. }
+            | Transaction::BurnChips { from, .. } => Some(*from),
+            Transaction::MintChips { .. } => None,
+        }
+    }
+
+    pub fn nonce(&self) -> Option<u64> {
+        match self {
+            Transaction::TransferChips { nonce, .. }
+            | Transaction::TransferNft { nonce, .. }
+            | Transaction::BurnChips { nonce, .. } => Some(*nonce),
+            Transaction::MintChips { .. } => None,
+        }
+    }
+
+    /// Canonical bytes, all little-endian:
+    /// transfers are tag + from + to + 32-byte payload + nonce,
+    /// mints are tag + to + 32-byte amount, burns are tag + from + 32-byte amount + nonce.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (tag, from, to, value, nonce) = match self {
+            Transaction::TransferChips { from, to, amount, nonce } => (0x00u8, from, to, amount, nonce),
+            Transaction::TransferNft { from, to, nft_id, nonce }   => (0x01u8, from, to, nft_id, nonce),
+            Transaction::MintChips { to, amount } => {
+                let mut bytes = Vec::with_capacity(1 + 16 * 2 + 32);
+                bytes.push(0x02);
+                bytes.extend_from_slice(&to.block.to_le_bytes());
+                bytes.extend_from_slice(&to.tx.to_le_bytes());
+                bytes.extend_from_slice(&amount.to_le_bytes());
+                return bytes;
+            }
+            Transaction::BurnChips { from, amount, nonce } => {
+                let mut bytes = Vec::with_capacity(1 + 16 * 2 + 32 + 8);
+                bytes.push(0x03);
+                bytes.extend_from_slice(&from.block.to_le_bytes());
+                bytes.extend_from_slice(&from.tx.to_le_bytes());
+                bytes.extend_from_slice(&amount.to_le_bytes());
+                bytes.extend_from_slice(&nonce.to_le_bytes());
+                return bytes;
+            }
+        };
+        let mut bytes = Vec::with_capacity(1 + 16 * 4 + 32 + 8);
+        bytes.push(tag);
+        bytes.extend_from_slice(&from.block.to_le_bytes());
+        bytes.extend_from_slice(&from.tx.to_le_bytes());
+        bytes.extend_from_slice(&to.block.to_le_bytes());
+        bytes.extend_from_slice(&to.tx.to_le_bytes());
+        bytes.extend_from_slice(&value.to_le_bytes());
+        bytes.extend_from_slice(&nonce.to_le_bytes());
+        bytes
+    }
+
+    /// Content hash used to recognise rebroadcasts. This is also the 32-byte
+    /// message a sender signs: SHA-256 over `to_bytes()`.
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::hash(&self.to_bytes())
+    }
+}
+
+/// A transaction plus the sender's BIP-340 schnorr signature over `tx.hash()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub tx:        Transaction,
+    pub signature: [u8; 64],
+    /// x-only secp256k1 key; must equal the sender's registered key.
+    pub pubkey:    [u8; 32],
+}
+
+impl SignedTransaction {
+    fn verify(&self, state: &State) -> Result<(), MempoolError> {
+        let sender = self.tx.sender().ok_or(MempoolError::NoSigner)?;
+        let registered = state.key(&sender).ok_or(MempoolError::UnregisteredKey(sender))?;
+        if *registered != self.pubkey {
+            return Err(MempoolError::WrongKey(sender));
+        }
+        let pubkey = XOnlyPublicKey::from_slice(&self.pubkey).map_err(|_| MempoolError::BadSignature)?;
+        let signature = schnorr::Signature::from_slice(&self.signature).map_err(|_| MempoolError::BadSignature)?;
+        Secp256k1::verification_only()
+            .verify_schnorr(&signature, &Message::from_digest(self.tx.hash()), &pubkey)
+            .map_err(|_| MempoolError::BadSignature)
+    }
+}
+
+/// Reasons a transaction is refused admission to the pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MempoolError {
+    /// An identical transaction is already pooled.
+    Duplicate,
+    UnknownPlayer(AlkaneId),
+    InsufficientChips { from: AlkaneId, balance: U256, amount: U256 },
+    UnknownNft(U256),
+    NotNftOwner { nft_id: U256, owner: AlkaneId, from: AlkaneId },
+    /// A chip transfer to its own sender, which the circuit rejects.
+    SelfTransfer(AlkaneId),
+    /// Nonce the sender has already used on L2.
+    StaleNonce { sender: AlkaneId, expected: u64, got: u64 },
+    /// Nonce past the sender's next one, counting what is already pooled.
+    NonceGap   { sender: AlkaneId, expected: u64, got: u64 },
+    /// The transaction kind has no player sender to sign it.
+    NoSigner,
+    UnregisteredKey(AlkaneId),
+    /// The attached key is not the one registered for the sender.
+    WrongKey(AlkaneId),
+    BadSignature,
+}
+
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MempoolError::Duplicate => write!(f, "duplicate transaction"),
+            MempoolError::UnknownPlayer(id) => write!(f, "unknown player {}:{}", id.block, id.tx),
+            MempoolError::InsufficientChips { from, balance, amount } =>
+                write!(f, "{}:{} holds {balance} chips, cannot send {amount}", from.block, from.tx),
+            MempoolError::UnknownNft(id) => write!(f, "unknown nft {id}"),
+            MempoolError::NotNftOwner { nft_id, owner, from } =>
+                write!(f, "nft {nft_id} is owned by {}:{}, not {}:{}", owner.block, owner.tx, from.block, from.tx),
+            MempoolError::SelfTransfer(id) => write!(f, "{}:{} cannot transfer chips to itself", id.block, id.tx),
+            MempoolError::StaleNonce { sender, expected, got } =>
+                write!(f, "stale nonce {got} for {}:{} (expected {expected})", sender.block, sender.tx),
+            MempoolError::NonceGap { sender, expected, got } =>
+                write!(f, "nonce gap {got} for {}:{} (expected at most {expected})", sender.block, sender.tx),
+            MempoolError::NoSigner => write!(f, "transaction has no signer"),
+            MempoolError::UnregisteredKey(id) => write!(f, "no key registered for {}:{}", id.block, id.tx),
+            MempoolError::WrongKey(id) => write!(f, "key does not belong to {}:{}", id.block, id.tx),
+            MempoolError::BadSignature => write!(f, "invalid signature"),
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+/// A pooled transaction and the clock reading it was admitted at.
+struct Entry {
+    tx:          Transaction,
+    inserted_at: u64,
+}
+
+/// Seconds since the Unix epoch; the default mempool clock.
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
 }
 
 pub struct Mempool {
-    transactions: VecDeque<Transaction>,
+    transactions: VecDeque<Entry>,
+    seen:         HashSet<[u8; 32]>,
+    /// Stamps new entries; any monotonic unit works (seconds, block height).
+    clock:        fn() -> u64,
+    /// Entries older than this are never handed out; `None` keeps them forever.
+    ttl:          Option<u64>,
+}
+
+impl Default for Mempool {
+    fn default() -> Self { Self::new() }
 }
 
 impl Mempool {
     pub fn new() -> Self {
         Self {
             transactions: VecDeque::new(),
+            seen:         HashSet::new(),
+            clock:        unix_now,
+            ttl:          None,
         }
     }
 
-    pub fn add_transaction(&mut self, transaction: Transaction) {
-        self.transactions.push_back(transaction);
+    /// Replaces the wall clock, e.g. with the current block height.
+    pub fn with_clock(mut self, clock: fn() -> u64) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Makes `get_transactions` and `peek` skip entries older than `ttl` clock units.
+    pub fn with_ttl(mut self, ttl: u64) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Queues `transaction`; returns `false` if an identical one is already pooled.
+    pub fn add_transaction(&mut self, transaction: Transaction) -> bool {
+        if !self.seen.insert(transaction.hash()) {
+            return false;
+        }
+        self.transactions.push_back(Entry { tx: transaction, inserted_at: (self.clock)() });
+        true
+    }
+
+    /// Like `add_transaction`, but first checks `transaction` against `state` so
+    /// transfers the circuit would panic on never reach a block. Use the raw
+    /// `add_transaction` only for trusted, pre-validated sources.
+    pub fn add_validated(&mut self, transaction: Transaction, state: &State) -> Result<(), MempoolError> {
+        let pooled = transaction.sender().map_or(0, |sender| self.pooled_from(&sender));
+        validate(&transaction, state, pooled)?;
+        if !self.add_transaction(transaction) {
+            return Err(MempoolError::Duplicate);
+        }
+        Ok(())
     }
 
+    /// Checks the sender's signature, then admits the transaction as
+    /// `add_validated` does. This is the entry point for user-submitted txs.
+    pub fn add_signed(&mut self, signed: SignedTransaction, state: &State) -> Result<(), MempoolError> {
+        signed.verify(state)?;
+        self.add_validated(signed.tx, state)
+    }
+
+    /// Drops every entry inserted more than `ttl` clock units before `now`
+    /// and returns how many were removed.
+    pub fn prune_expired(&mut self, now: u64, ttl: u64) -> usize {
+        let before = self.transactions.len();
+        let seen = &mut self.seen;
+        self.transactions.retain(|entry| {
+            let fresh = !expired(entry, now, ttl);
+            if !fresh {
+                seen.remove(&entry.tx.hash());
+            }
+            fresh
+        });
+        before - self.transactions.len()
+    }
+
+    /// The up-to-`n` transactions the next `get_transactions(n)` would return,
+    /// in the same order, without removing them.
+    pub fn peek(&self, n: usize) -> Vec<Transaction> {
+        let now = (self.clock)();
+        self.transactions
+            .iter()
+            .filter(|entry| !self.ttl.is_some_and(|ttl| expired(entry, now, ttl)))
+            .take(n)
+            .map(|entry| entry.tx.clone())
+            .collect()
+    }
+
+    /// How many pooled transactions `sender` authored.
+    fn pooled_from(&self, sender: &AlkaneId) -> u64 {
+        self.transactions.iter().filter(|entry| entry.tx.sender() == Some(*sender)).count() as u64
+    }
+
+    /// Pooled entries, including expired ones not yet pruned.
+    pub fn len(&self) -> usize { self.transactions.len() }
+    pub fn is_empty(&self) -> bool { self.transactions.is_empty() }
+
+    /// Removes `txs` from the pool, e.g. once a block that `peek`ed them has
+    /// proven. Returns how many were still pooled.
+    pub fn remove(&mut self, txs: &[Transaction]) -> usize {
+        let hashes: HashSet<[u8; 32]> = txs.iter().map(Transaction::hash).collect();
+        let before = self.transactions.len();
+        let seen = &mut self.seen;
+        self.transactions.retain(|entry| {
+            let hash = entry.tx.hash();
+            let keep = !hashes.contains(&hash);
+            if !keep {
+                seen.remove(&hash);
+            }
+            keep
+        });
+        before - self.transactions.len()
+    }
+
+    /// Removes and returns up to `n` transactions in selection order, first
+    /// pruning anything past the configured TTL.
     pub fn get_transactions(&mut self, n: usize) -> Vec<Transaction> {
-        self.transactions.drain(0..n.min(self.transactions.len())).collect()
+        if let Some(ttl) = self.ttl {
+            self.prune_expired((self.clock)(), ttl);
+        }
+        let drained: Vec<Transaction> = self
+            .transactions
+            .drain(0..n.min(self.transactions.len()))
+            .map(|entry| entry.tx)
+            .collect();
+        for tx in &drained {
+            self.seen.remove(&tx.hash());
+        }
+        drained
     }
-}
\ No newline at end of file
+}
+
+fn expired(entry: &Entry, now: u64, ttl: u64) -> bool {
+    now.saturating_sub(entry.inserted_at) > ttl
+}
+
+/// `pooled` is how many of the sender's transactions already wait in the pool,
+/// so a sender may queue consecutive nonces ahead of the next block.
+fn validate(transaction: &Transaction, state: &State, pooled: u64) -> Result<(), MempoolError> {
+    if let Some((sender, nonce)) = transaction.sender().zip(transaction.nonce()) {
+        let expected = state.next_nonce(&sender);
+        if nonce < expected {
+            return Err(MempoolError::StaleNonce { sender, expected, got: nonce });
+        }
+        if nonce > expected + pooled {
+            return Err(MempoolError::NonceGap { sender, expected: expected + pooled, got: nonce });
+        }
+    }
+    match transaction {
+        Transaction::TransferChips { from, to, amount, .. } => {
+            if from == to {
+                return Err(MempoolError::SelfTransfer(*from));
+            }
+            check_chips(state, from, amount)?;
+            state.player(to).ok_or(MempoolError::UnknownPlayer(*to))?;
+        }
+        Transaction::BurnChips { from, amount, .. } => check_chips(state, from, amount)?,
+        Transaction::TransferNft { from, nft_id, .. } => {
+            let nft = state.nft(nft_id).ok_or(MempoolError::UnknownNft(*nft_id))?;
+            if nft.owner != *from {
+                return Err(MempoolError::NotNftOwner { nft_id: *nft_id, owner: nft.owner, from: *from });
+            }
+        }
+        // The recipient may be new to L2; Cairo creates the player on first mint.
+        Transaction::MintChips { .. } => {}
+    }
+    Ok(())
+}
+
+fn check_chips(state: &State, from: &AlkaneId, amount: &U256) -> Result<(), MempoolError> {
+    let sender = state.player(from).ok_or(MempoolError::UnknownPlayer(*from))?;
+    if sender.chips_balance < *amount {
+        return Err(MempoolError::InsufficientChips {
+            from:    *from,
+            balance: sender.chips_balance,
+            amount:  *amount,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{OrbitalNft, Player};
+    use bitcoin::secp256k1::Keypair;
+    use std::cell::Cell;
+
+    fn id(block: u128, tx: u128) -> AlkaneId {
+        AlkaneId { block, tx }
+    }
+
+    /// p1 (1:1) holds 100 chips and nft 42, p2 (1:2) holds 50 chips.
+    fn seeded_state() -> State {
+        let mut state = State::new();
+        state.upsert_player(Player { id: id(1, 1), chips_balance: U256::from(100u128) });
+        state.upsert_player(Player { id: id(1, 2), chips_balance: U256::from(50u128) });
+        state.upsert_nft(OrbitalNft { id: U256::from(42u128), owner: id(1, 1), data: [0; 32] });
+        state.commit();
+        state
+    }
+
+    #[test]
+    fn duplicate_transfer_stored_once() {
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 10u128.into(), nonce: 0 };
+
+        assert!(mempool.add_transaction(tx.clone()));
+        assert!(!mempool.add_transaction(tx.clone()));
+
+        assert_eq!(mempool.get_transactions(usize::MAX), vec![tx]);
+    }
+
+    #[test]
+    fn duplicate_nft_transfer_stored_once() {
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferNft { from: id(1, 1), to: id(1, 2), nft_id: U256::from(42u128), nonce: 0 };
+
+        assert!(mempool.add_transaction(tx.clone()));
+        assert!(!mempool.add_transaction(tx.clone()));
+
+        assert_eq!(mempool.get_transactions(usize::MAX).len(), 1);
+    }
+
+    #[test]
+    fn chips_and_nft_with_same_fields_are_distinct() {
+        let mut mempool = Mempool::new();
+        let chips = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: U256::from(42u128), nonce: 0 };
+        let nft   = Transaction::TransferNft   { from: id(1, 1), to: id(1, 2), nft_id: U256::from(42u128), nonce: 0 };
+
+        assert!(mempool.add_transaction(chips));
+        assert!(mempool.add_transaction(nft));
+        assert_eq!(mempool.get_transactions(usize::MAX).len(), 2);
+    }
+
+    #[test]
+    fn same_transfer_with_new_nonce_is_distinct() {
+        let mut mempool = Mempool::new();
+        let first  = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 10u128.into(), nonce: 0 };
+        let second = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 10u128.into(), nonce: 1 };
+
+        assert!(mempool.add_transaction(first));
+        assert!(mempool.add_transaction(second));
+    }
+
+    #[test]
+    fn drained_transaction_can_be_requeued() {
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 10u128.into(), nonce: 0 };
+
+        assert!(mempool.add_transaction(tx.clone()));
+        mempool.get_transactions(usize::MAX);
+        assert!(mempool.add_transaction(tx));
+    }
+
+    #[test]
+    fn peek_leaves_pool_unchanged() {
+        let mut mempool = Mempool::new();
+        for nonce in 0..3 {
+            mempool.add_transaction(Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 1u128.into(), nonce });
+        }
+
+        let peeked = mempool.peek(2);
+
+        assert_eq!(peeked.len(), 2);
+        assert_eq!(mempool.len(), 3);
+        assert_eq!(mempool.peek(2), peeked);
+        assert_eq!(mempool.get_transactions(2), peeked);
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn peek_on_empty_pool() {
+        let mempool = Mempool::new();
+        assert!(mempool.is_empty());
+        assert!(mempool.peek(usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn validated_rejects_insufficient_chips() {
+        let state = seeded_state();
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferChips { from: id(1, 2), to: id(1, 1), amount: 51u128.into(), nonce: 0 };
+
+        assert_eq!(
+            mempool.add_validated(tx, &state),
+            Err(MempoolError::InsufficientChips {
+                from:    id(1, 2),
+                balance: U256::from(50u128),
+                amount:  U256::from(51u128),
+            })
+        );
+        assert!(mempool.get_transactions(usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn validated_rejects_wrong_nft_owner() {
+        let state = seeded_state();
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferNft { from: id(1, 2), to: id(1, 2), nft_id: U256::from(42u128), nonce: 0 };
+
+        assert_eq!(
+            mempool.add_validated(tx, &state),
+            Err(MempoolError::NotNftOwner { nft_id: U256::from(42u128), owner: id(1, 1), from: id(1, 2) })
+        );
+        assert!(mempool.get_transactions(usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn validated_accepts_valid_transfer() {
+        let state = seeded_state();
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 100u128.into(), nonce: 0 };
+
+        assert_eq!(mempool.add_validated(tx.clone(), &state), Ok(()));
+        assert_eq!(mempool.add_validated(tx.clone(), &state), Err(MempoolError::Duplicate));
+        assert_eq!(mempool.get_transactions(usize::MAX), vec![tx]);
+    }
+
+    #[test]
+    fn validated_accepts_mint_to_new_player() {
+        let state = seeded_state();
+        let mut mempool = Mempool::new();
+        let tx = Transaction::MintChips { to: id(9, 9), amount: 25u128.into() };
+
+        assert_eq!(mempool.add_validated(tx.clone(), &state), Ok(()));
+        assert_eq!(mempool.get_transactions(usize::MAX), vec![tx]);
+    }
+
+    #[test]
+    fn validated_rejects_self_transfer() {
+        let state = seeded_state();
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferChips { from: id(1, 1), to: id(1, 1), amount: 10u128.into(), nonce: 0 };
+
+        assert_eq!(mempool.add_validated(tx, &state), Err(MempoolError::SelfTransfer(id(1, 1))));
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn validated_rejects_burn_over_balance() {
+        let state = seeded_state();
+        let mut mempool = Mempool::new();
+        let tx = Transaction::BurnChips { from: id(1, 2), amount: 51u128.into(), nonce: 0 };
+
+        assert_eq!(
+            mempool.add_validated(tx, &state),
+            Err(MempoolError::InsufficientChips {
+                from:    id(1, 2),
+                balance: U256::from(50u128),
+                amount:  U256::from(51u128),
+            })
+        );
+    }
+
+    #[test]
+    fn validated_rejects_used_nonce() {
+        let mut state = seeded_state();
+        state.check_and_bump_nonce(id(1, 1), 0).unwrap();
+        let mut mempool = Mempool::new();
+
+        assert_eq!(
+            mempool.add_validated(chips(0), &state),
+            Err(MempoolError::StaleNonce { sender: id(1, 1), expected: 1, got: 0 })
+        );
+        assert_eq!(mempool.add_validated(chips(1), &state), Ok(()));
+    }
+
+    #[test]
+    fn validated_queues_consecutive_nonces_but_not_gaps() {
+        let state = seeded_state();
+        let mut mempool = Mempool::new();
+
+        assert_eq!(mempool.add_validated(chips(0), &state), Ok(()));
+        assert_eq!(mempool.add_validated(chips(1), &state), Ok(()));
+        assert_eq!(
+            mempool.add_validated(chips(3), &state),
+            Err(MempoolError::NonceGap { sender: id(1, 1), expected: 2, got: 3 })
+        );
+        assert_eq!(mempool.len(), 2);
+    }
+
+    thread_local! {
+        static NOW: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// Per-test clock; each `#[test]` runs on its own thread.
+    fn test_clock() -> u64 {
+        NOW.with(Cell::get)
+    }
+
+    fn set_clock(now: u64) {
+        NOW.with(|c| c.set(now));
+    }
+
+    fn chips(nonce: u64) -> Transaction {
+        Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 1u128.into(), nonce }
+    }
+
+    #[test]
+    fn prune_expired_keeps_fresh_entries() {
+        let mut mempool = Mempool::new().with_clock(test_clock);
+        set_clock(100);
+        mempool.add_transaction(chips(0));
+        mempool.add_transaction(chips(1));
+        set_clock(160);
+        mempool.add_transaction(chips(2));
+
+        set_clock(200);
+        assert_eq!(mempool.prune_expired(test_clock(), 60), 2);
+
+        assert_eq!(mempool.get_transactions(usize::MAX), vec![chips(2)]);
+        // pruned entries may be resubmitted
+        assert!(mempool.add_transaction(chips(0)));
+    }
+
+    #[test]
+    fn get_transactions_skips_expired_entries() {
+        let mut mempool = Mempool::new().with_clock(test_clock).with_ttl(10);
+        set_clock(0);
+        mempool.add_transaction(chips(0));
+        set_clock(5);
+        mempool.add_transaction(chips(1));
+
+        set_clock(12);
+        assert_eq!(mempool.peek(usize::MAX), vec![chips(1)]);
+        assert_eq!(mempool.get_transactions(usize::MAX), vec![chips(1)]);
+        assert!(mempool.is_empty());
+    }
+
+    fn keypair(seed: u8) -> Keypair {
+        Keypair::from_seckey_slice(&Secp256k1::new(), &[seed; 32]).unwrap()
+    }
+
+    fn sign(tx: Transaction, keys: &Keypair) -> SignedTransaction {
+        let sig = Secp256k1::new().sign_schnorr_no_aux_rand(&Message::from_digest(tx.hash()), keys);
+        SignedTransaction { tx, signature: *sig.as_ref(), pubkey: keys.x_only_public_key().0.serialize() }
+    }
+
+    /// `seeded_state` with p1's key registered.
+    fn keyed_state(p1_keys: &Keypair) -> State {
+        let mut state = seeded_state();
+        state.register_key(id(1, 1), p1_keys.x_only_public_key().0.serialize());
+        state
+    }
+
+    #[test]
+    fn signed_accepts_valid_signature() {
+        let keys = keypair(1);
+        let state = keyed_state(&keys);
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 10u128.into(), nonce: 0 };
+
+        assert_eq!(mempool.add_signed(sign(tx.clone(), &keys), &state), Ok(()));
+        assert_eq!(mempool.get_transactions(usize::MAX), vec![tx]);
+    }
+
+    #[test]
+    fn signed_rejects_tampered_amount() {
+        let keys = keypair(1);
+        let state = keyed_state(&keys);
+        let mut mempool = Mempool::new();
+        let mut signed = sign(
+            Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 10u128.into(), nonce: 0 },
+            &keys,
+        );
+        signed.tx = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 90u128.into(), nonce: 0 };
+
+        assert_eq!(mempool.add_signed(signed, &state), Err(MempoolError::BadSignature));
+        assert!(mempool.get_transactions(usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn signed_rejects_wrong_key() {
+        let state = keyed_state(&keypair(1));
+        let mut mempool = Mempool::new();
+        let tx = Transaction::TransferChips { from: id(1, 1), to: id(1, 2), amount: 10u128.into(), nonce: 0 };
+
+        assert_eq!(mempool.add_signed(sign(tx, &keypair(2)), &state), Err(MempoolError::WrongKey(id(1, 1))));
+        assert!(mempool.get_transactions(usize::MAX).is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Continuous block production.
+
+use crate::mempool::Mempool;
+use crate::prover::{Prover, ProverError};
+use crate::state::State;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Every `interval`, takes up to `max_txs` from `mempool`, proves them as one
+/// block and advances `state` to the proven post-state, sending the block's
+/// SATC witness payload on `payloads`. Rounds with an empty mempool are
+/// skipped rather than proving empty blocks.
+///
+/// Transactions are only peeked until their block proves, so on a proving
+/// error they are still in `mempool`.
+///
+/// Returns the latest state once the receiving side of `payloads` is dropped,
+/// or the first proving error.
+pub async fn run_loop(
+    prover: Prover,
+    mut state: State,
+    mempool: Arc<Mutex<Mempool>>,
+    interval: Duration,
+    max_txs: usize,
+    payloads: mpsc::Sender<Vec<u8>>,
+) -> Result<State, ProverError> {
+    loop {
+        if payloads.is_closed() {
+            return Ok(state);
+        }
+
+        let txs = mempool.lock().expect("mempool lock poisoned").peek(max_txs);
+        if !txs.is_empty() {
+            let block = prover.prove_block(&txs, &state)?;
+            mempool.lock().expect("mempool lock poisoned").remove(&txs);
+            let payload = block.witness_payload();
+            state = block.post_state;
+            if payloads.send(payload).await.is_err() {
+                return Ok(state);
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::Transaction;
+    use crate::state::{AlkaneId, Player};
+    use ethnum::U256;
+
+    #[tokio::test]
+    async fn root_advances_once_per_non_empty_round() {
+        let p1 = AlkaneId { block: 1, tx: 1 };
+        let p2 = AlkaneId { block: 1, tx: 2 };
+        let mut state = State::new();
+        state.upsert_player(Player { id: p1, chips_balance: U256::from(100u128) });
+        state.upsert_player(Player { id: p2, chips_balance: U256::from(50u128) });
+        state.commit();
+        let pre_root = state.root().expect("root must exist");
+
+        // One tx per round: two non-empty rounds, then empty ones.
+        let mempool = Arc::new(Mutex::new(Mempool::new()));
+        {
+            let mut pool = mempool.lock().unwrap();
+            pool.add_transaction(Transaction::TransferChips { from: p1, to: p2, amount: 10u128.into(), nonce: 0 });
+            pool.add_transaction(Transaction::TransferChips { from: p1, to: p2, amount: 5u128.into(), nonce: 1 });
+        }
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let producer = run_loop(Prover::new(), state, mempool.clone(), Duration::from_millis(10), 1, tx);
+        let consumer = async move {
+            let mut roots = Vec::new();
+            for _ in 0..2 {
+                let payload = rx.recv().await.expect("block payload");
+                roots.push(payload[payload.len() - 32..].to_vec());
+            }
+            // several empty rounds elapse without producing a block
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(rx.try_recv().is_err());
+            roots
+        };
+
+        let (state, roots) = tokio::join!(producer, consumer);
+        let state = state.expect("blocks must prove");
+
+        assert_eq!(roots.len(), 2);
+        assert_ne!(roots[0], pre_root.to_vec());
+        assert_ne!(roots[1], roots[0]);
+        assert_eq!(state.root().map(|r| r.to_vec()), Some(roots[1].clone()));
+        assert_eq!(state.next_nonce(&p1), 2);
+        assert!(mempool.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_block_stays_in_the_mempool() {
+        let p1 = AlkaneId { block: 1, tx: 1 };
+        let p2 = AlkaneId { block: 1, tx: 2 };
+        let mut state = State::new();
+        state.upsert_player(Player { id: p1, chips_balance: U256::from(10u128) });
+        state.upsert_player(Player { id: p2, chips_balance: U256::ZERO });
+        state.commit();
+
+        // Overdraws p1, so the Cairo run panics.
+        let overdraft = Transaction::TransferChips { from: p1, to: p2, amount: 500u128.into(), nonce: 0 };
+        let mempool = Arc::new(Mutex::new(Mempool::new()));
+        mempool.lock().unwrap().add_transaction(overdraft.clone());
+
+        let (tx, _rx) = mpsc::channel(1);
+        let result = run_loop(Prover::new(), state, mempool.clone(), Duration::from_millis(10), 1, tx).await;
+
+        assert!(result.is_err());
+        assert_eq!(mempool.lock().unwrap().peek(1), vec![overdraft]);
+    }
+}
@@ -1,19 +1,22 @@
-use crate::helpers::{decode_nfts, decode_players, encode_nfts, encode_players, encode_txs};
+use crate::helpers::{decode_nfts, decode_players, encode_nfts, encode_players, encode_txs, DecodeError};
 use crate::mempool::Transaction;
-use crate::state::State;
+use crate::state::{State, StateError, EMPTY_ROOT};
 use bincode::enc::write::Writer;
 use cairo1_run::error::Error;
 use cairo1_run::{cairo_run_program, Cairo1RunConfig, FuncArg};
 use cairo_air::utils::ProofFormat;
-use cairo_air::PreProcessedTraceVariant;
+use cairo_air::{CairoProof, PreProcessedTraceVariant};
 use cairo_lang_sierra::program::Program as SierraProgram;
 use cairo_vm::stdlib::collections::HashMap;
 use cairo_vm::types::layout_name::LayoutName;
+use cairo_vm::types::relocatable::MaybeRelocatable;
 use cairo_vm::vm::errors::trace_errors::TraceError;
 use cairo_vm::Felt252;
 use serde::Serialize;
+use std::fmt;
 use std::io::Write;
 use std::path::PathBuf;
+use std::time::Instant;
 use stwo_cairo_adapter::builtins::MemorySegmentAddresses;
 use stwo_cairo_adapter::memory::{MemoryBuilder, MemoryConfig, MemoryEntry as StwoMemoryEntry};
 use stwo_cairo_adapter::vm_import::{adapt_to_stwo_input, RelocatedTraceEntry as StwoRelocatedTraceEntry};
@@ -26,6 +29,7 @@ use stwo_cairo_prover::stwo_prover::core::pcs::PcsConfig;
 use stwo_cairo_prover::stwo_prover::core::vcs::blake2_merkle::Blake2sMerkleChannel;
 use stwo_cairo_prover::stwo_prover::core::vcs::ops::MerkleHasher;
 use stwo_cairo_serialize::CairoSerialize;
+use starknet_ff::FieldElement;
 use bytemuck::cast_slice;
 
 // Vec-backed writer to capture Cairo encoders' output in-memory.
@@ -41,19 +45,260 @@ impl<'a> Writer for VecWriter<'a> {
     }
 }
 
+#[derive(Debug)]
+pub enum ProverError {
+    /// The Cairo run (or a step of the proving pipeline) failed.
+    Cairo(Error),
+    /// The block failed the Rust-side state checks before reaching Cairo.
+    State(StateError),
+    /// The circuit's return values did not decode into a state.
+    Decode(DecodeError),
+    /// The freshly generated proof did not pass local verification.
+    SelfVerifyFailed(String),
+    /// The pre-state has leaves that were never committed, so no old root to prove from.
+    Uncommitted,
+}
+
+impl From<Error> for ProverError {
+    fn from(err: Error) -> Self { ProverError::Cairo(err) }
+}
+
+impl From<StateError> for ProverError {
+    fn from(err: StateError) -> Self { ProverError::State(err) }
+}
+
+impl From<DecodeError> for ProverError {
+    fn from(err: DecodeError) -> Self { ProverError::Decode(err) }
+}
+
+impl fmt::Display for ProverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProverError::Cairo(err) => write!(f, "cairo: {err}"),
+            ProverError::State(err) => write!(f, "state: {err}"),
+            ProverError::Decode(err) => write!(f, "decode: {err}"),
+            ProverError::SelfVerifyFailed(err) => write!(f, "SELF_VERIFY_FAILED: {err}"),
+            ProverError::Uncommitted => write!(f, "pre-state has no committed root"),
+        }
+    }
+}
+
+impl std::error::Error for ProverError {}
+
+/// Result of proving one block.
+pub struct ProvedBlock {
+    /// State decoded from the Cairo return values, with nonces advanced.
+    pub post_state: State,
+    /// Committed root of the pre-state, read before the block is applied.
+    pub old_root:   [u8; 32],
+    pub new_root:   [u8; 32],
+    /// `CairoSerialize`d proof, as the verifier deserializes it.
+    pub proof:      Vec<FieldElement>,
+    pub variant:    PreProcessedTraceVariant,
+}
+
+impl ProvedBlock {
+    /// The SATC witness payload the verifier contract reads from input 0:
+    /// `"SATC"`, version 3, variant byte, u32 BE felt count, 32-byte BE felts,
+    /// u32 BE root length, new root, u32 BE root length, old root.
+    pub fn witness_payload(&self) -> Vec<u8> {
+        let variant: u8 = match self.variant {
+            PreProcessedTraceVariant::Canonical => 0,
+            PreProcessedTraceVariant::CanonicalWithoutPedersen => 1,
+        };
+        let mut out = Vec::with_capacity(4 + 2 + 4 + 32 * self.proof.len() + 4 + 32 + 4 + 32);
+        out.extend_from_slice(b"SATC");
+        out.push(3);
+        out.push(variant);
+        out.extend_from_slice(&(self.proof.len() as u32).to_be_bytes());
+        for felt in &self.proof {
+            out.extend_from_slice(&felt.to_bytes_be());
+        }
+        out.extend_from_slice(&(self.new_root.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.new_root);
+        out.extend_from_slice(&(self.old_root.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.old_root);
+        out
+    }
+}
+
+/// Where the time and bytes of one `prove_with_metrics` call went.
+#[derive(Clone, Debug, Default)]
+pub struct ProveMetrics {
+    /// Cairo VM execution, trace included.
+    pub cairo_run_ms:     f64,
+    /// Trace/memory encoding and the stwo adapter.
+    pub adapt_ms:         f64,
+    /// `prove_cairo` plus proof serialization.
+    pub prove_ms:         f64,
+    pub proof_felt_count: usize,
+    pub payload_bytes:    usize,
+}
+
+fn ms_since(start: Instant) -> f64 { start.elapsed().as_secs_f64() * 1000.0 }
+
+struct RunOutput {
+    metrics:     ProveMetrics,
+    ret:         Vec<MaybeRelocatable>,
+    proof_felts: Vec<FieldElement>,
+    variant:     PreProcessedTraceVariant,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProverConfig {
+    /// Run the on-chain verifier (see `self_verify`) on each proof before returning it, so
+    /// adapter or serialization bugs surface here rather than on-chain.
+    pub verify_after_prove: bool,
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self { verify_after_prove: cfg!(debug_assertions) }
+    }
+}
+
+/// Checks the proof as it ships: rebuilds it from `proof_felts` with the
+/// verifier contract's deserializer, then runs that contract's verifier on it.
+/// Serialization or felt-order bugs fail here instead of on-chain.
+fn self_verify(proof_felts: &[FieldElement], variant: PreProcessedTraceVariant) -> Result<(), ProverError> {
+    use cairo_air_verifier_lite::air::CairoProof as LiteCairoProof;
+    use cairo_air_verifier_lite::PreProcessedTraceVariant as LiteVariant;
+    use stwo_cairo_serialize_lite::CairoDeserialize;
+    use stwo_lite::core::vcs::blake2_merkle::{Blake2sMerkleChannel, Blake2sMerkleHasher};
+
+    let proof: LiteCairoProof<Blake2sMerkleHasher> = LiteCairoProof::deserialize(&mut proof_felts.iter());
+    let variant = match variant {
+        PreProcessedTraceVariant::Canonical => LiteVariant::Canonical,
+        PreProcessedTraceVariant::CanonicalWithoutPedersen => LiteVariant::CanonicalWithoutPedersen,
+    };
+    cairo_air_verifier_lite::verifier::verify_cairo::<Blake2sMerkleChannel>(proof, variant)
+        .map_err(|err| ProverError::SelfVerifyFailed(format!("{err:?}")))
+}
+
+/// Rebuilds the post-state from the circuit's serialized `GameState`.
+fn decode_state(ret: &[MaybeRelocatable]) -> Result<State, DecodeError> {
+    let mut it = ret.iter();
+    it.next();
+    let players_out = decode_players(&mut it)?;
+    let nfts_out = decode_nfts(&mut it)?;
+
+    let mut new_state = State::new();
+    for p in players_out {
+        new_state.upsert_player(p);
+    }
+    for n in nfts_out {
+        new_state.upsert_nft(n);
+    }
+    new_state.commit(); // seals the Merkle tree
+    Ok(new_state)
+}
+
 pub struct Prover {
     sierra_program: SierraProgram,
+    config:         ProverConfig,
+}
+
+impl Default for Prover {
+    fn default() -> Self { Self::new() }
 }
 
 impl Prover {
     pub fn new() -> Self {
+        Self::with_config(ProverConfig::default())
+    }
+
+    pub fn with_config(config: ProverConfig) -> Self {
         let raw_json: &[u8] = include_bytes!("../../circuits/target/dev/circuits.sierra.json");
         let sierra_program = serde_json::from_slice(raw_json)
             .expect("circuits.sierra.json must be valid Sierra JSON");
-        Self { sierra_program }
+        Self { sierra_program, config }
+    }
+
+    /// Proves the block and returns `(old_root, new_root, proof)`: the committed
+    /// root of `state`, the root the block transitions it to, and the serialized proof.
+    pub fn prove(
+        &self,
+        transactions: &[Transaction],
+        state: &State,
+    ) -> Result<([u8; 32], [u8; 32], Vec<FieldElement>), ProverError> {
+        let block = self.prove_block(transactions, state)?;
+        Ok((block.old_root, block.new_root, block.proof))
     }
 
-    pub fn prove(&self, transactions: &[Transaction], state: &State) -> Result<[u8; 32], Error> {
+    /// The root `prove` would return, computed by the Rust mirror of the circuit
+    /// (`State::apply_block`) without running Cairo or generating a STARK.
+    /// Cheap enough to validate a block before committing to proving it.
+    pub fn compute_root_only(&self, transactions: &[Transaction], state: &State) -> Result<[u8; 32], ProverError> {
+        Ok(state.clone().apply_block(transactions)?)
+    }
+
+    /// Proves only the transactions that apply, in order, on top of `state` per
+    /// the Rust mirror (`State::apply_tx`), so one invalid transaction drops
+    /// out instead of panicking the whole block in Cairo. A dropped transaction
+    /// also drops any later one from its sender whose nonce it would have
+    /// filled. Returns the included transactions, the new root and the proof.
+    ///
+    /// The proof is the `CairoSerialize`d felts, like `prove` and `ProvedBlock`:
+    /// that is the form the witness payload and `self_verify` consume, and the
+    /// typed `CairoProof` is not kept past serialization.
+    pub fn prove_filtered(
+        &self,
+        transactions: &[Transaction],
+        state: &State,
+    ) -> Result<(Vec<Transaction>, [u8; 32], Vec<FieldElement>), ProverError> {
+        let mut mirror = state.clone();
+        let included: Vec<Transaction> = transactions
+            .iter()
+            .filter(|tx| mirror.apply_tx(tx).is_ok())
+            .cloned()
+            .collect();
+        let block = self.prove_block(&included, state)?;
+        Ok((included, block.new_root, block.proof))
+    }
+
+    /// Like `prove`, but also hands back the post-state the root commits to.
+    pub fn prove_block(&self, transactions: &[Transaction], state: &State) -> Result<ProvedBlock, ProverError> {
+        Ok(self.prove_with_metrics(transactions, state)?.0)
+    }
+
+    /// `prove_block` plus per-stage timings and proof sizes.
+    pub fn prove_with_metrics(
+        &self,
+        transactions: &[Transaction],
+        state: &State,
+    ) -> Result<(ProvedBlock, ProveMetrics), ProverError> {
+        // The root the verifier holds for the pre-state, before anything is applied.
+        let old_root = state.committed_root().ok_or(ProverError::Uncommitted)?;
+        // Replay protection: each sender's nonces must continue from the pre-state.
+        let mut scratch = state.clone();
+        for tx in transactions {
+            if let (Some(sender), Some(nonce)) = (tx.sender(), tx.nonce()) {
+                scratch.check_and_bump_nonce(sender, nonce)?;
+            }
+        }
+        let RunOutput { mut metrics, ret, proof_felts, variant } = self.run(transactions, state)?;
+        if self.config.verify_after_prove {
+            self_verify(&proof_felts, variant)?;
+        }
+        let proven = decode_state(&ret)?;
+        let mut post_state = state.clone();
+        for p in proven.players_list() {
+            post_state.upsert_player(p);
+        }
+        for n in proven.nfts_list() {
+            post_state.upsert_nft(n);
+        }
+        post_state.inherit_nonces(&scratch);
+        post_state.commit();
+        let new_root = post_state.root().unwrap_or(EMPTY_ROOT);
+        let block = ProvedBlock { post_state, old_root, new_root, proof: proof_felts, variant };
+        metrics.proof_felt_count = block.proof.len();
+        metrics.payload_bytes = block.witness_payload().len();
+        Ok((block, metrics))
+    }
+
+    /// Runs and proves the circuit, returning its raw return values and the proof.
+    fn run(&self, transactions: &[Transaction], state: &State) -> Result<RunOutput, Error> {
         // flatten GameState
         let players = encode_players(&state.players_list());
 
@@ -62,8 +307,8 @@ impl Prover {
         let tx_felts = encode_txs(transactions);
 
         let player_count = players.len() / 4;
-        let nfts_count = nfts.len() / 4;
-        let tx_count = tx_felts.len() / 7;
+        let nfts_count = nfts.len() / 6;
+        let tx_count = transactions.len();
 
         let mut all: Vec<Felt252> =
             Vec::with_capacity(3 + players.len() + nfts.len() + tx_felts.len());
@@ -90,8 +335,13 @@ impl Prover {
             dynamic_layout_params: None,
         };
 
+        let mut metrics = ProveMetrics::default();
+        let started = Instant::now();
         match cairo_run_program(&self.sierra_program, cairo_run_config) {
             Ok((_runner, ret, _serial)) => {
+                metrics.cairo_run_ms = ms_since(started);
+                let started = Instant::now();
+
                 // Prepare public input in-memory.
                 let public_input = _runner.get_air_public_input()?;
 
@@ -150,6 +400,7 @@ impl Prover {
                     &memory_segments,
                     PublicSegmentContext::bootloader_context(),
                 ).unwrap();
+                metrics.adapt_ms = ms_since(started);
 
                 // println!("prover_input: {:?}", &prover_input);
                 let prover_params = default_prod_prover_parameters();
@@ -158,33 +409,13 @@ impl Prover {
 
                 let proof_path = PathBuf::from("./example_proof.json");
 
-                let _cairo_proof = Prover::run_inner::<Blake2sMerkleChannel>(prover_input, prover_params.pcs_config, prover_params.preprocessed_trace, proof_path, proof_format).unwrap();
-
-
-                let mut it = ret.iter();
-                it.next();
-
-                println!("return {:?}", it);
-                let players_out = decode_players(&mut it);
-                let nfts_out = decode_nfts(&mut it);
+                let variant = prover_params.preprocessed_trace;
+                let started = Instant::now();
+                let (_proof, proof_felts) = Prover::run_inner::<Blake2sMerkleChannel>(prover_input, prover_params.pcs_config, variant, proof_path, proof_format).unwrap();
+                metrics.prove_ms = ms_since(started);
 
-                let mut new_state = State::new();
-                for p in players_out {
-                    new_state.upsert_player(p);
-                }
-                for n in nfts_out {
-                    new_state.upsert_nft(n);
-                }
-                new_state.commit(); // seals the Merkle tree
-                let new_root = new_state.root().expect("new state must have a root");
 
-                /* ---------------------------------------------------
-                 * 5.  Debug print – BEFORE vs AFTER
-                 * ------------------------------------------------ */
-                //println!("--- OLD STATE ---\n{state:#?}");
-                //println!("--- NEW STATE ---\n{new_state:#?}");
-
-                Ok(new_root)
+                Ok(RunOutput { metrics, ret, proof_felts, variant })
             }
 
             Err(Error::RunPanic(panic_data)) => {
@@ -211,19 +442,24 @@ impl Prover {
         }
     }
 
+    /// Proves `vm_output`, writes the proof to `proof_path` in `proof_format`,
+    /// and returns it both as-is and `CairoSerialize`d.
     pub fn run_inner<MC: MerkleChannel>(
         vm_output: ProverInput,
         pcs_config: PcsConfig,
         preprocessed_trace: PreProcessedTraceVariant,
         proof_path: PathBuf,
         proof_format: ProofFormat,
-    ) -> Result<(), Error>
+    ) -> Result<(CairoProof<MC::H>, Vec<FieldElement>), Error>
     where
         SimdBackend: BackendForChannel<MC>,
         MC::H: Serialize,
         <MC::H as MerkleHasher>::Hash: CairoSerialize,
     {
         let proof = prove_cairo::<MC>(vm_output, pcs_config, preprocessed_trace).unwrap();
+        let mut serialized: Vec<FieldElement> = Vec::new();
+        CairoSerialize::serialize(&proof, &mut serialized);
+
         let mut proof_file = std::fs::File::create(proof_path)?;
 
         match proof_format {
@@ -231,11 +467,8 @@ impl Prover {
                 proof_file.write_all(sonic_rs::to_string_pretty(&proof).unwrap().as_bytes())?;
             }
             ProofFormat::CairoSerde => {
-                let mut serialized: Vec<starknet_ff::FieldElement> = Vec::new();
-                CairoSerialize::serialize(&proof, &mut serialized);
-
                 let hex_strings: Vec<String> = serialized
-                    .into_iter()
+                    .iter()
                     .map(|felt| format!("0x{:x}", felt))
                     .collect();
 
@@ -244,6 +477,6 @@ impl Prover {
             }
         }
 
-        Ok(())
+        Ok((proof, serialized))
     }
 }
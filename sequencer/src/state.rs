@@ -1,9 +1,12 @@
-use rs_merkle::{algorithms::Sha256, Hasher, MerkleTree};
+use crate::mempool::Transaction;
+use rs_merkle::{algorithms::Sha256, Hasher, MerkleProof, MerkleTree};
 use ethnum::U256;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
 /// Matches the Cairo struct 1 : 1
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct AlkaneId {
     pub block: u128,
     pub tx:    u128,
@@ -27,66 +30,634 @@ impl Default for Player {
 pub struct OrbitalNft {
     pub id:    U256,
     pub owner: AlkaneId,
+    /// Game attributes (staking flag, level, ...) committed alongside ownership;
+    /// a little-endian `u256` on the Cairo side.
+    pub data:  [u8; 32],
 }
 
+/// Rejections raised by the Rust-side state checks.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StateError {
+    /// Nonce already used by this sender.
+    StaleNonce { sender: AlkaneId, expected: u64, got: u64 },
+    /// Nonce skips ahead of the next expected one.
+    NonceGap   { sender: AlkaneId, expected: u64, got: u64 },
+    UnknownPlayer(AlkaneId),
+    UnknownNft(U256),
+    /// Sender's balance is below the transferred amount.
+    InsufficientBalance { sender: AlkaneId, balance: U256, amount: U256 },
+    /// Crediting the recipient would exceed `U256::MAX`.
+    Overflow { recipient: AlkaneId },
+    NotOwner { nft_id: U256, owner: AlkaneId, from: AlkaneId },
+    /// A chip transfer whose sender is also its recipient; the circuit rejects these.
+    SelfTransfer(AlkaneId),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::StaleNonce { sender, expected, got } =>
+                write!(f, "stale nonce {got} for {}:{} (expected {expected})", sender.block, sender.tx),
+            StateError::NonceGap { sender, expected, got } =>
+                write!(f, "nonce gap {got} for {}:{} (expected {expected})", sender.block, sender.tx),
+            StateError::UnknownPlayer(id) => write!(f, "unknown player {}:{}", id.block, id.tx),
+            StateError::UnknownNft(id) => write!(f, "unknown nft {id}"),
+            StateError::InsufficientBalance { sender, balance, amount } =>
+                write!(f, "{}:{} holds {balance} chips, cannot send {amount}", sender.block, sender.tx),
+            StateError::Overflow { recipient } =>
+                write!(f, "balance of {}:{} would overflow", recipient.block, recipient.tx),
+            StateError::NotOwner { nft_id, owner, from } =>
+                write!(f, "nft {nft_id} is owned by {}:{}, not {}:{}", owner.block, owner.tx, from.block, from.tx),
+            StateError::SelfTransfer(id) => write!(f, "{}:{} cannot transfer chips to itself", id.block, id.tx),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Root of a state with no leaves, e.g. genesis, which the tree itself has none for.
+pub const EMPTY_ROOT: [u8; 32] = [0; 32];
+
 #[derive(Clone)]
 pub struct State {
     tree:    MerkleTree<Sha256>,
     players: HashMap<AlkaneId, Player>,
     nfts:    HashMap<U256, OrbitalNft>,
+    /// Next expected nonce per sender; not part of the Merkle commitment.
+    nonces:  HashMap<AlkaneId, u64>,
+    /// x-only schnorr key each player signs with; not part of the Merkle commitment.
+    keys:    HashMap<AlkaneId, [u8; 32]>,
+}
+
+impl Default for State {
+    fn default() -> Self { Self::new() }
 }
 
 impl State {
     /// Empty tree / maps – cheapest constructor.
     pub fn new() -> Self {
-        Self { tree: MerkleTree::new(), players: HashMap::new(), nfts: HashMap::new() }
+        Self {
+            tree:    MerkleTree::new(),
+            players: HashMap::new(),
+            nfts:    HashMap::new(),
+            nonces:  HashMap::new(),
+            keys:    HashMap::new(),
+        }
     }
 
     /* ---------- Mutators  ---------- */
 
     pub fn upsert_player(&mut self, player: Player) {
-        let leaf_hash = hash_player(&player);
-        self.tree.insert(leaf_hash);
         self.players.insert(player.id, player);          // overwrites if exists
     }
 
     pub fn upsert_nft(&mut self, nft: OrbitalNft) {
-        let leaf_hash = hash_nft(&nft);
-        self.tree.insert(leaf_hash);
         self.nfts.insert(nft.id, nft);
     }
 
-    /// Finalises current batch – call once per block.
-    pub fn commit(&mut self) { self.tree.commit(); }
+    /// Moves `amount` chips from `from` to `to`, or leaves the state untouched
+    /// if `from == to`, the sender is short or the recipient's balance would overflow.
+    pub fn apply_transfer_chips(&mut self, from: AlkaneId, to: AlkaneId, amount: U256) -> Result<(), StateError> {
+        if from == to {
+            return Err(StateError::SelfTransfer(from));
+        }
+        let balance = self.players.get(&from).ok_or(StateError::UnknownPlayer(from))?.chips_balance;
+        let to_balance = self.players.get(&to).ok_or(StateError::UnknownPlayer(to))?.chips_balance;
+        let new_from = balance
+            .checked_sub(amount)
+            .ok_or(StateError::InsufficientBalance { sender: from, balance, amount })?;
+        let new_to = to_balance.checked_add(amount).ok_or(StateError::Overflow { recipient: to })?;
+
+        self.players.get_mut(&from).unwrap().chips_balance = new_from;
+        self.players.get_mut(&to).unwrap().chips_balance = new_to;
+        Ok(())
+    }
+
+    /// Hands `nft_id` from `from` to `to`; `from` must be the current owner.
+    pub fn apply_transfer_nft(&mut self, from: AlkaneId, to: AlkaneId, nft_id: U256) -> Result<(), StateError> {
+        let nft = self.nfts.get_mut(&nft_id).ok_or(StateError::UnknownNft(nft_id))?;
+        if nft.owner != from {
+            return Err(StateError::NotOwner { nft_id, owner: nft.owner, from });
+        }
+        nft.owner = to;
+        Ok(())
+    }
+
+    /// Credits a bridge mint, creating the player on first deposit as Cairo does.
+    pub fn apply_mint_chips(&mut self, to: AlkaneId, amount: U256) -> Result<(), StateError> {
+        let player = self.players.entry(to).or_insert(Player { id: to, chips_balance: U256::ZERO });
+        player.chips_balance = player
+            .chips_balance
+            .checked_add(amount)
+            .ok_or(StateError::Overflow { recipient: to })?;
+        Ok(())
+    }
+
+    /// Destroys `amount` of `from`'s chips.
+    pub fn apply_burn_chips(&mut self, from: AlkaneId, amount: U256) -> Result<(), StateError> {
+        let player = self.players.get_mut(&from).ok_or(StateError::UnknownPlayer(from))?;
+        let balance = player.chips_balance;
+        player.chips_balance = balance
+            .checked_sub(amount)
+            .ok_or(StateError::InsufficientBalance { sender: from, balance, amount })?;
+        Ok(())
+    }
+
+    /// Applies one transaction without committing. Its nonce must be the
+    /// sender's next; on error `self` is left untouched, nonce included.
+    pub fn apply_tx(&mut self, tx: &Transaction) -> Result<(), StateError> {
+        let sender_nonce = tx.sender().zip(tx.nonce());
+        if let Some((sender, nonce)) = sender_nonce {
+            self.check_nonce(sender, nonce)?;
+        }
+        match tx {
+            Transaction::TransferChips { from, to, amount, .. } => self.apply_transfer_chips(*from, *to, *amount)?,
+            Transaction::TransferNft { from, to, nft_id, .. } => self.apply_transfer_nft(*from, *to, *nft_id)?,
+            Transaction::MintChips { to, amount } => self.apply_mint_chips(*to, *amount)?,
+            Transaction::BurnChips { from, amount, .. } => self.apply_burn_chips(*from, *amount)?,
+        }
+        if let Some((sender, nonce)) = sender_nonce {
+            self.nonces.insert(sender, nonce + 1);
+        }
+        Ok(())
+    }
+
+    /// Rust-native equivalent of the Cairo block transition: checks nonces and
+    /// applies `txs` in order, then commits and returns the new root. Either the
+    /// whole block applies or `self` is left untouched.
+    pub fn apply_block(&mut self, txs: &[Transaction]) -> Result<[u8; 32], StateError> {
+        let mut next = self.clone();
+        for tx in txs {
+            next.apply_tx(tx)?;
+        }
+        next.commit();
+        *self = next;
+        Ok(self.root().unwrap_or(EMPTY_ROOT))
+    }
+
+    /// Accepts `nonce` only if it is exactly the sender's next expected one,
+    /// then advances the counter. Rejects replays and out-of-order nonces.
+    pub fn check_and_bump_nonce(&mut self, sender: AlkaneId, nonce: u64) -> Result<(), StateError> {
+        self.check_nonce(sender, nonce)?;
+        self.nonces.insert(sender, nonce + 1);
+        Ok(())
+    }
+
+    /// `check_and_bump_nonce` without the bump.
+    fn check_nonce(&self, sender: AlkaneId, nonce: u64) -> Result<(), StateError> {
+        let expected = self.next_nonce(&sender);
+        if nonce < expected {
+            return Err(StateError::StaleNonce { sender, expected, got: nonce });
+        }
+        if nonce > expected {
+            return Err(StateError::NonceGap { sender, expected, got: nonce });
+        }
+        Ok(())
+    }
+
+    /// Binds `id` to the key its transactions must be signed with.
+    pub fn register_key(&mut self, id: AlkaneId, pubkey: [u8; 32]) {
+        self.keys.insert(id, pubkey);
+    }
+
+    /// Takes over `other`'s nonce counters and keys, e.g. when a decoded post-state
+    /// replaces the pre-state it was proven from.
+    pub fn inherit_nonces(&mut self, other: &State) {
+        self.nonces = other.nonces.clone();
+        self.keys = other.keys.clone();
+    }
+
+    /// Finalises current batch – call once per block. The tree is rebuilt
+    /// from players then NFTs, each sorted by id, so the root depends only on
+    /// the contents and not on insertion or update history.
+    pub fn commit(&mut self) {
+        let leaves: Vec<[u8; 32]> = self
+            .players_list()
+            .iter()
+            .map(hash_player)
+            .chain(self.nfts_list().iter().map(hash_nft))
+            .collect();
+        self.tree = MerkleTree::from_leaves(&leaves);
+    }
 
     /* ---------- Getters  ---------- */
 
     pub fn player(&self, id: &AlkaneId) -> Option<&Player> { self.players.get(id) }
     pub fn nft(&self, id: &U256)       -> Option<&OrbitalNft> { self.nfts.get(id) }
+    pub fn next_nonce(&self, id: &AlkaneId) -> u64 { self.nonces.get(id).copied().unwrap_or(0) }
+    pub fn key(&self, id: &AlkaneId)   -> Option<&[u8; 32]> { self.keys.get(id) }
 
     pub fn root(&self) -> Option<[u8; 32]> { self.tree.root() }
 
-    /// Flat lists the prover expects.
-    pub fn players_list(&self) -> Vec<Player>     { self.players.values().cloned().collect() }
-    pub fn nfts_list(&self)    -> Vec<OrbitalNft> { self.nfts.values().cloned().collect() }
+    /// `root`, or `EMPTY_ROOT` when there is nothing to commit. `None` only if
+    /// leaves exist but were never committed.
+    pub fn committed_root(&self) -> Option<[u8; 32]> {
+        self.root().or_else(|| (self.players.is_empty() && self.nfts.is_empty()).then_some(EMPTY_ROOT))
+    }
+    pub fn leaf_count(&self) -> usize { self.tree.leaves_len() }
+
+    /// Serialized `MerkleProof<Sha256>` for the committed leaf at `index`;
+    /// leaves are players then NFTs, each in `*_list` order.
+    pub fn inclusion_proof(&self, index: usize) -> Vec<u8> {
+        self.tree.proof(&[index]).to_bytes()
+    }
+
+    /// Checks `leaf` (see `hash_player` / `hash_nft`) sits at `index` of a
+    /// `total_leaves`-leaf tree with `root`, given `inclusion_proof` bytes.
+    /// Needs only the root, not the tree.
+    pub fn verify_inclusion(
+        root: [u8; 32],
+        leaf: [u8; 32],
+        index: usize,
+        total_leaves: usize,
+        proof_bytes: &[u8],
+    ) -> bool {
+        match MerkleProof::<Sha256>::from_bytes(proof_bytes) {
+            Ok(proof) => proof.verify(root, &[index], &[leaf], total_leaves),
+            Err(_) => false,
+        }
+    }
+
+    /// Flat lists the prover expects, sorted by id.
+    pub fn players_list(&self) -> Vec<Player> {
+        let mut players: Vec<Player> = self.players.values().cloned().collect();
+        players.sort_by_key(|p| p.id);
+        players
+    }
+
+    pub fn nfts_list(&self) -> Vec<OrbitalNft> {
+        let mut nfts: Vec<OrbitalNft> = self.nfts.values().cloned().collect();
+        nfts.sort_by_key(|n| n.id);
+        nfts
+    }
+}
+
+/* ---------- Debug JSON export ---------- */
+
+/// Ids are `"block:tx"` and 256-bit values decimal strings, as in the
+/// `satcity_txs` view.
+#[derive(Serialize, Deserialize)]
+struct StateJson {
+    players: Vec<PlayerJson>,
+    nfts:    Vec<NftJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlayerJson {
+    id:            String,
+    chips_balance: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NftJson {
+    id:    String,
+    owner: String,
+    /// Hex; absent in dumps written before NFTs carried data.
+    #[serde(default)]
+    data:  String,
+}
+
+fn id_to_string(id: &AlkaneId) -> String { format!("{}:{}", id.block, id.tx) }
+
+fn id_from_str(s: &str) -> Result<AlkaneId, serde_json::Error> {
+    let bad = || <serde_json::Error as serde::de::Error>::custom(format!("bad alkane id {s:?}"));
+    let (block, tx) = s.split_once(':').ok_or_else(bad)?;
+    Ok(AlkaneId { block: block.parse().map_err(|_| bad())?, tx: tx.parse().map_err(|_| bad())? })
+}
+
+fn u256_from_str(s: &str) -> Result<U256, serde_json::Error> {
+    U256::from_str_radix(s, 10)
+        .map_err(|_| <serde_json::Error as serde::de::Error>::custom(format!("bad u256 {s:?}")))
+}
+
+fn data_from_str(s: &str) -> Result<[u8; 32], serde_json::Error> {
+    if s.is_empty() {
+        return Ok([0; 32]);
+    }
+    let mut data = [0u8; 32];
+    hex::decode_to_slice(s, &mut data)
+        .map_err(|_| <serde_json::Error as serde::de::Error>::custom(format!("bad nft data {s:?}")))?;
+    Ok(data)
+}
+
+impl State {
+    /// Pretty JSON of all players and NFTs, each sorted by id, so equal states
+    /// export byte-identical text. For inspection and diffing only: nonces and
+    /// keys are not included.
+    pub fn to_json(&self) -> String {
+        let dump = StateJson {
+            players: self
+                .players_list()
+                .iter()
+                .map(|p| PlayerJson { id: id_to_string(&p.id), chips_balance: p.chips_balance.to_string() })
+                .collect(),
+            nfts: self
+                .nfts_list()
+                .iter()
+                .map(|n| NftJson { id: n.id.to_string(), owner: id_to_string(&n.owner), data: hex::encode(n.data) })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&dump).expect("state JSON serializes")
+    }
+
+    /// Rebuilds a state from `to_json` output; call `commit` before reading the root.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let dump: StateJson = serde_json::from_str(json)?;
+        let mut state = State::new();
+        for p in dump.players {
+            state.upsert_player(Player { id: id_from_str(&p.id)?, chips_balance: u256_from_str(&p.chips_balance)? });
+        }
+        for n in dump.nfts {
+            state.upsert_nft(OrbitalNft {
+                id:    u256_from_str(&n.id)?,
+                owner: id_from_str(&n.owner)?,
+                data:  data_from_str(&n.data)?,
+            });
+        }
+        Ok(state)
+    }
 }
 
 /* ---------- Helpers: deterministic hashing ---------- */
 
-fn hash_player(p: &Player) -> [u8; 32] {
-    let mut bytes = Vec::with_capacity(1 + 16 + 16 + 32);      // tag + id + balance
-    bytes.push(0x00);                                          // player-tag
-    bytes.extend_from_slice(&p.id.block.to_le_bytes());        // little-endian per Rust docs :contentReference[oaicite:5]{index=5}
-    bytes.extend_from_slice(&p.id.tx.to_le_bytes());
-    bytes.extend_from_slice(&p.chips_balance.to_le_bytes());   // U256::to_le_bytes() → [u8; 32] :contentReference[oaicite:6]{index=6}
+/// Domain string every leaf preimage starts with.
+pub const LEAF_DOMAIN: &[u8] = b"SATCITY-LEAF";
+/// Bumped whenever a leaf's field list changes.
+pub const LEAF_VERSION: u8 = 1;
+const PLAYER_LEAF: u8 = 0x00;
+const NFT_LEAF: u8 = 0x01;
+
+/// `SHA-256(LEAF_DOMAIN || LEAF_VERSION || kind || (u32 LE len || field)*)`.
+/// Each field carries its length, so adding or widening a field can never make
+/// two leaves (of either kind) share a preimage.
+///
+/// The circuit does not hash leaves today; the root is built here only. If it
+/// ever does, it must use exactly this preimage.
+fn hash_leaf(kind: u8, fields: &[&[u8]]) -> [u8; 32] {
+    let len = LEAF_DOMAIN.len() + 2 + fields.iter().map(|f| 4 + f.len()).sum::<usize>();
+    let mut bytes = Vec::with_capacity(len);
+    bytes.extend_from_slice(LEAF_DOMAIN);
+    bytes.push(LEAF_VERSION);
+    bytes.push(kind);
+    for field in fields {
+        bytes.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(field);
+    }
     Sha256::hash(&bytes)
 }
 
-fn hash_nft(n: &OrbitalNft) -> [u8; 32] {
-    let mut bytes = Vec::with_capacity(1 + 32 + 16 + 16);
-    bytes.push(0x01);                                          // nft-tag
-    bytes.extend_from_slice(&n.id.to_le_bytes());
-    bytes.extend_from_slice(&n.owner.block.to_le_bytes());
-    bytes.extend_from_slice(&n.owner.tx.to_le_bytes());
-    Sha256::hash(&bytes)
+/// Merkle leaf committed for a player: id block, id tx, balance (all LE).
+pub fn hash_player(p: &Player) -> [u8; 32] {
+    hash_leaf(
+        PLAYER_LEAF,
+        &[&p.id.block.to_le_bytes(), &p.id.tx.to_le_bytes(), &p.chips_balance.to_le_bytes()],
+    )
+}
+
+/// Merkle leaf committed for an NFT: id, owner block, owner tx (all LE), data.
+pub fn hash_nft(n: &OrbitalNft) -> [u8; 32] {
+    hash_leaf(
+        NFT_LEAF,
+        &[&n.id.to_le_bytes(), &n.owner.block.to_le_bytes(), &n.owner.tx.to_le_bytes(), &n.data],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENDER: AlkaneId = AlkaneId { block: 1, tx: 1 };
+    const RECIPIENT: AlkaneId = AlkaneId { block: 1, tx: 2 };
+
+    fn funded(sender: U256, recipient: U256) -> State {
+        let mut state = State::new();
+        state.upsert_player(Player { id: SENDER, chips_balance: sender });
+        state.upsert_player(Player { id: RECIPIENT, chips_balance: recipient });
+        state
+    }
+
+    #[test]
+    fn accepts_sequential_nonces() {
+        let mut state = State::new();
+        assert_eq!(state.check_and_bump_nonce(SENDER, 0), Ok(()));
+        assert_eq!(state.check_and_bump_nonce(SENDER, 1), Ok(()));
+        assert_eq!(state.next_nonce(&SENDER), 2);
+    }
+
+    #[test]
+    fn rejects_stale_nonce() {
+        let mut state = State::new();
+        state.check_and_bump_nonce(SENDER, 0).unwrap();
+        assert_eq!(
+            state.check_and_bump_nonce(SENDER, 0),
+            Err(StateError::StaleNonce { sender: SENDER, expected: 1, got: 0 })
+        );
+        assert_eq!(state.next_nonce(&SENDER), 1);
+    }
+
+    #[test]
+    fn rejects_nonce_gap() {
+        let mut state = State::new();
+        assert_eq!(
+            state.check_and_bump_nonce(SENDER, 2),
+            Err(StateError::NonceGap { sender: SENDER, expected: 0, got: 2 })
+        );
+        assert_eq!(state.next_nonce(&SENDER), 0);
+    }
+
+    #[test]
+    fn rejected_tx_keeps_its_nonce_unspent() {
+        let mut state = funded(U256::from(10u128), U256::ZERO);
+        let overdraft = Transaction::BurnChips { from: SENDER, amount: U256::from(11u128), nonce: 0 };
+        let burn = Transaction::BurnChips { from: SENDER, amount: U256::from(3u128), nonce: 0 };
+
+        assert!(matches!(state.apply_tx(&overdraft), Err(StateError::InsufficientBalance { .. })));
+        assert_eq!(state.next_nonce(&SENDER), 0);
+        state.apply_tx(&burn).unwrap();
+        assert_eq!(state.next_nonce(&SENDER), 1);
+        assert_eq!(state.player(&SENDER).unwrap().chips_balance, U256::from(7u128));
+    }
+
+    #[test]
+    fn nonces_are_tracked_per_sender() {
+        let mut state = State::new();
+        let other = AlkaneId { block: 1, tx: 2 };
+        state.check_and_bump_nonce(SENDER, 0).unwrap();
+        assert_eq!(state.check_and_bump_nonce(other, 0), Ok(()));
+    }
+
+    #[test]
+    fn transfer_updates_both_balances() {
+        let mut state = funded(U256::from(100u128), U256::from(50u128));
+        assert_eq!(state.apply_transfer_chips(SENDER, RECIPIENT, U256::from(30u128)), Ok(()));
+        assert_eq!(state.player(&SENDER).unwrap().chips_balance, U256::from(70u128));
+        assert_eq!(state.player(&RECIPIENT).unwrap().chips_balance, U256::from(80u128));
+    }
+
+    #[test]
+    fn transfer_rejects_underflow() {
+        let mut state = funded(U256::from(10u128), U256::ZERO);
+        assert_eq!(
+            state.apply_transfer_chips(SENDER, RECIPIENT, U256::from(11u128)),
+            Err(StateError::InsufficientBalance { sender: SENDER, balance: U256::from(10u128), amount: U256::from(11u128) })
+        );
+        assert_eq!(state.player(&SENDER).unwrap().chips_balance, U256::from(10u128));
+        assert_eq!(state.player(&RECIPIENT).unwrap().chips_balance, U256::ZERO);
+    }
+
+    #[test]
+    fn transfer_rejects_overflow() {
+        let mut state = funded(U256::from(10u128), U256::MAX);
+        assert_eq!(
+            state.apply_transfer_chips(SENDER, RECIPIENT, U256::ONE),
+            Err(StateError::Overflow { recipient: RECIPIENT })
+        );
+        assert_eq!(state.player(&SENDER).unwrap().chips_balance, U256::from(10u128));
+        assert_eq!(state.player(&RECIPIENT).unwrap().chips_balance, U256::MAX);
+    }
+
+    #[test]
+    fn transfer_rejects_self_transfer() {
+        let mut state = funded(U256::from(10u128), U256::ZERO);
+        assert_eq!(
+            state.apply_transfer_chips(SENDER, SENDER, U256::from(5u128)),
+            Err(StateError::SelfTransfer(SENDER))
+        );
+        assert_eq!(state.player(&SENDER).unwrap().chips_balance, U256::from(10u128));
+    }
+
+    #[test]
+    fn nft_transfer_checks_owner() {
+        let mut state = funded(U256::ZERO, U256::ZERO);
+        let nft_id = U256::from(42u128);
+        state.upsert_nft(OrbitalNft { id: nft_id, owner: SENDER, data: [0; 32] });
+
+        assert_eq!(
+            state.apply_transfer_nft(RECIPIENT, SENDER, nft_id),
+            Err(StateError::NotOwner { nft_id, owner: SENDER, from: RECIPIENT })
+        );
+        assert_eq!(state.apply_transfer_nft(SENDER, RECIPIENT, nft_id), Ok(()));
+        assert_eq!(state.nft(&nft_id).unwrap().owner, RECIPIENT);
+    }
+
+    #[test]
+    fn root_ignores_update_history() {
+        let mut applied = funded(U256::from(100u128), U256::from(50u128));
+        applied.apply_transfer_chips(SENDER, RECIPIENT, U256::from(30u128)).unwrap();
+        applied.commit();
+
+        let mut fresh = State::new();
+        fresh.upsert_player(Player { id: RECIPIENT, chips_balance: U256::from(80u128) });
+        fresh.upsert_player(Player { id: SENDER, chips_balance: U256::from(70u128) });
+        fresh.commit();
+
+        assert_eq!(applied.root(), fresh.root());
+    }
+
+    #[test]
+    fn player_leaf_matches_test_vector() {
+        let leaf = hash_player(&Player { id: SENDER, chips_balance: U256::from(100u128) });
+        assert_eq!(hex::encode(leaf), "6a85d2314e6b20664bab62297647d58dae4be80425c7bc162d3830ffb7824260");
+    }
+
+    #[test]
+    fn nft_leaf_matches_test_vector() {
+        let leaf = hash_nft(&OrbitalNft { id: U256::from(42u128), owner: SENDER, data: [0; 32] });
+        assert_eq!(hex::encode(leaf), "4bb0abe9135b77c1c81c86a51aa3ebc79ab763eca4c8a624088c6df9c2107a5f");
+    }
+
+    #[test]
+    fn nft_data_is_bound_into_root() {
+        let root_with = |data: [u8; 32]| {
+            let mut state = funded(U256::from(1u128), U256::ZERO);
+            state.upsert_nft(OrbitalNft { id: U256::from(42u128), owner: SENDER, data });
+            state.commit();
+            state.root().unwrap()
+        };
+        let mut levelled = [0u8; 32];
+        levelled[0] = 1;
+
+        assert_ne!(root_with([0; 32]), root_with(levelled));
+        assert_eq!(root_with(levelled), root_with(levelled));
+    }
+
+    #[test]
+    fn json_round_trip_keeps_root_and_order() {
+        let mut state = State::new();
+        state.upsert_player(Player { id: RECIPIENT, chips_balance: U256::MAX });
+        state.upsert_player(Player { id: SENDER, chips_balance: U256::from(100u128) });
+        state.upsert_nft(OrbitalNft { id: U256::from(42u128), owner: RECIPIENT, data: [0; 32] });
+        state.upsert_nft(OrbitalNft { id: U256::from(7u128), owner: SENDER, data: [3; 32] });
+        state.commit();
+
+        let json = state.to_json();
+        assert_eq!(json, state.to_json());
+        assert!(json.find("\"1:1\"").unwrap() < json.find("\"1:2\"").unwrap());
+
+        let mut imported = State::from_json(&json).unwrap();
+        imported.commit();
+        assert_eq!(imported.root(), state.root());
+        assert_eq!(imported.to_json(), json);
+    }
+
+    #[test]
+    fn from_json_rejects_bad_id() {
+        let json = r#"{"players":[{"id":"1-1","chips_balance":"5"}],"nfts":[]}"#;
+        assert!(State::from_json(json).is_err());
+    }
+
+    /// Players 1:1, 1:2, 1:3 then nft 42: four leaves.
+    fn committed() -> State {
+        let mut state = funded(U256::from(100u128), U256::from(50u128));
+        state.upsert_player(Player { id: AlkaneId { block: 1, tx: 3 }, chips_balance: U256::ONE });
+        state.upsert_nft(OrbitalNft { id: U256::from(42u128), owner: SENDER, data: [0; 32] });
+        state.commit();
+        state
+    }
+
+    #[test]
+    fn failed_block_leaves_state_untouched() {
+        let mut state = funded(U256::from(10u128), U256::ZERO);
+        state.commit();
+        let root = state.root();
+        let txs = [
+            Transaction::TransferChips { from: SENDER, to: RECIPIENT, amount: U256::from(4u128), nonce: 0 },
+            Transaction::BurnChips { from: SENDER, amount: U256::from(7u128), nonce: 1 },
+        ];
+
+        let err = state.apply_block(&txs).unwrap_err();
+
+        assert!(matches!(err, StateError::InsufficientBalance { .. }));
+        assert_eq!(state.root(), root);
+        assert_eq!(state.player(&SENDER).unwrap().chips_balance, U256::from(10u128));
+        assert_eq!(state.next_nonce(&SENDER), 0);
+    }
+
+    #[test]
+    fn inclusion_proof_verifies() {
+        let state = committed();
+        let leaf = hash_player(state.player(&RECIPIENT).unwrap());
+
+        assert!(State::verify_inclusion(state.root().unwrap(), leaf, 1, state.leaf_count(), &state.inclusion_proof(1)));
+
+        let nft_leaf = hash_nft(state.nft(&U256::from(42u128)).unwrap());
+        assert!(State::verify_inclusion(state.root().unwrap(), nft_leaf, 3, 4, &state.inclusion_proof(3)));
+    }
+
+    #[test]
+    fn inclusion_rejects_wrong_leaf() {
+        let state = committed();
+        let forged = hash_player(&Player { id: RECIPIENT, chips_balance: U256::from(51u128) });
+
+        assert!(!State::verify_inclusion(state.root().unwrap(), forged, 1, 4, &state.inclusion_proof(1)));
+    }
+
+    #[test]
+    fn inclusion_rejects_wrong_index() {
+        let state = committed();
+        let leaf = hash_player(state.player(&RECIPIENT).unwrap());
+
+        assert!(!State::verify_inclusion(state.root().unwrap(), leaf, 2, 4, &state.inclusion_proof(1)));
+        assert!(!State::verify_inclusion(state.root().unwrap(), leaf, 1, 4, &[0xff; 7]));
+    }
 }
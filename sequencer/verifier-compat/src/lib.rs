@@ -0,0 +1,4 @@
+//! Pins the sequencer's prover to the on-chain verifier. The test in `tests/`
+//! proves a real block and hands its witness payload to the verifier crate's
+//! own parser and STARK check, so a change to the SATC framing or the felt
+//! order on either side fails it. Nothing is exported.
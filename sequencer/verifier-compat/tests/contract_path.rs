@@ -0,0 +1,28 @@
+use ethnum::U256;
+use sequencer::mempool::Transaction;
+use sequencer::prover::Prover;
+use sequencer::state::{AlkaneId, Player, State};
+use verifier::payload::build_roots_payload;
+
+#[test]
+fn proved_block_verifies_through_the_contract_path() {
+    let p1 = AlkaneId { block: 1, tx: 1 };
+    let p2 = AlkaneId { block: 1, tx: 2 };
+    let mut state = State::new();
+    state.upsert_player(Player { id: p1, chips_balance: U256::from(100u128) });
+    state.upsert_player(Player { id: p2, chips_balance: U256::from(50u128) });
+    state.commit();
+    let tx = Transaction::TransferChips { from: p1, to: p2, amount: 10u128.into(), nonce: 0 };
+
+    let block = Prover::new().prove_block(&[tx], &state).expect("block must prove");
+    let bytes = block.witness_payload();
+
+    // The contract's parser and `verify_cairo` accept exactly what the prover wrote.
+    let payload = verifier::check_payload(&bytes).expect("verifier must accept the payload");
+    assert_eq!(payload.felts, block.proof);
+    assert_eq!(payload.new_root, block.new_root.to_vec());
+    assert_eq!(payload.old_root, Some(block.old_root.to_vec()));
+
+    // Framing: the verifier would write the same bytes.
+    assert_eq!(build_roots_payload(payload.variant, &payload.felts, &block.new_root, &block.old_root), bytes);
+}